@@ -0,0 +1,168 @@
+//! The agreement between the annotators, to know how consistent they are before using their
+//! annotations as ground truth.
+//!
+//! Each line of a log is an item, rated by each annotator who annotated the log with the category
+//! they gave to the line (e.g. selected or not).
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// The agreement of the annotators on a set of items. A coefficient is `None` when it is not
+/// defined, e.g. when every annotator gave the same category to every item (there is no disagreement
+/// to expect).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Agreement {
+    /// Cohen's kappa, averaged over each pair of annotators
+    pub cohen: Option<f64>,
+    pub fleiss: Option<f64>,
+    /// Krippendorff's alpha, for nominal categories
+    pub krippendorff: Option<f64>,
+    /// The number of annotators who rated at least one item
+    pub annotators: usize,
+    /// The number of items rated by at least two annotators
+    pub items: usize,
+}
+
+impl Agreement {
+    /// The agreement on the items, `ratings[item][annotator]` being the category given by the
+    /// annotator to the item (`None` if the annotator did not rate it).
+    pub fn of<C: Copy + Eq + Hash>(ratings: &[Vec<Option<C>>]) -> Self {
+        let annotators = (0..ratings.iter().map(Vec::len).max().unwrap_or(0))
+            .filter(|&a| ratings.iter().any(|item| matches!(item.get(a), Some(Some(_)))))
+            .count();
+        Self {
+            cohen: cohen_kappa(ratings),
+            fleiss: fleiss_kappa(ratings),
+            krippendorff: krippendorff_alpha(ratings),
+            annotators,
+            items: ratings
+                .iter()
+                .filter(|item| item.iter().flatten().count() >= 2)
+                .count(),
+        }
+    }
+}
+
+/// The ratings of the lines of a log: whether each annotator selected each line. `selections` has
+/// the lines selected by each annotator, `None` for the annotators who did not annotate the log.
+pub fn line_ratings(lines: usize, selections: &[Option<&Vec<usize>>]) -> Vec<Vec<Option<bool>>> {
+    let selections = selections
+        .iter()
+        .map(|s| s.map(|s| s.iter().collect::<HashSet<_>>()))
+        .collect::<Vec<_>>();
+    (0..lines)
+        .map(|line| {
+            selections
+                .iter()
+                .map(|s| s.as_ref().map(|s| s.contains(&line)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Cohen's kappa of each pair of annotators on the items they both rated, averaged over the pairs
+/// for which it is defined (Light's kappa when there are more than two annotators).
+pub fn cohen_kappa<C: Copy + Eq + Hash>(ratings: &[Vec<Option<C>>]) -> Option<f64> {
+    let annotators = ratings.iter().map(Vec::len).max().unwrap_or(0);
+    let mut kappas = vec![];
+    for a in 0..annotators {
+        for b in a + 1..annotators {
+            let pairs = ratings
+                .iter()
+                .filter_map(|item| Some((item.get(a).copied()??, item.get(b).copied()??)))
+                .collect::<Vec<_>>();
+            kappas.extend(pair_kappa(&pairs));
+        }
+    }
+    if kappas.is_empty() {
+        None
+    } else {
+        Some(kappas.iter().sum::<f64>() / kappas.len() as f64)
+    }
+}
+
+fn pair_kappa<C: Copy + Eq + Hash>(pairs: &[(C, C)]) -> Option<f64> {
+    if pairs.is_empty() {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let observed = pairs.iter().filter(|(a, b)| a == b).count() as f64 / n;
+    let first = count(pairs.iter().map(|p| p.0));
+    let second = count(pairs.iter().map(|p| p.1));
+    let expected = first
+        .iter()
+        .map(|(c, n)| n * second.get(c).unwrap_or(&0.0))
+        .sum::<f64>()
+        / (n * n);
+    kappa(observed, expected)
+}
+
+/// Fleiss' kappa, on the items rated by at least two annotators. The items do not need to be rated
+/// by the same number of annotators: the agreement on each item is computed with its own number of
+/// ratings.
+pub fn fleiss_kappa<C: Copy + Eq + Hash>(ratings: &[Vec<Option<C>>]) -> Option<f64> {
+    let mut agreement = 0.0;
+    let mut items = 0;
+    let mut totals = HashMap::new();
+    for item in ratings {
+        let counts = count(item.iter().flatten().copied());
+        let n = counts.values().sum::<f64>();
+        if n < 2.0 {
+            continue;
+        }
+        agreement += (counts.values().map(|k| k * k).sum::<f64>() - n) / (n * (n - 1.0));
+        items += 1;
+        for (c, k) in counts {
+            *totals.entry(c).or_insert(0.0) += k;
+        }
+    }
+    if items == 0 {
+        return None;
+    }
+    let n = totals.values().sum::<f64>();
+    let expected = totals.values().map(|k| (k / n) * (k / n)).sum::<f64>();
+    kappa(agreement / items as f64, expected)
+}
+
+/// Krippendorff's alpha for nominal categories, on the items rated by at least two annotators
+/// (i.e. the pairable values).
+pub fn krippendorff_alpha<C: Copy + Eq + Hash>(ratings: &[Vec<Option<C>>]) -> Option<f64> {
+    // the sum of the off-diagonal cells of the coincidence matrix
+    let mut disagreement = 0.0;
+    let mut totals = HashMap::new();
+    for item in ratings {
+        let counts = count(item.iter().flatten().copied());
+        let m = counts.values().sum::<f64>();
+        if m < 2.0 {
+            continue;
+        }
+        disagreement += (m * m - counts.values().map(|k| k * k).sum::<f64>()) / (m - 1.0);
+        for (c, k) in counts {
+            *totals.entry(c).or_insert(0.0) += k;
+        }
+    }
+    let n = totals.values().sum::<f64>();
+    let expected = n * n - totals.values().map(|k| k * k).sum::<f64>();
+    if expected == 0.0 {
+        None
+    } else {
+        Some(1.0 - (n - 1.0) * disagreement / expected)
+    }
+}
+
+fn kappa(observed: f64, expected: f64) -> Option<f64> {
+    if expected >= 1.0 {
+        None
+    } else {
+        Some((observed - expected) / (1.0 - expected))
+    }
+}
+
+fn count<C: Eq + Hash>(values: impl Iterator<Item = C>) -> HashMap<C, f64> {
+    values.fold(HashMap::new(), |mut counts, c| {
+        *counts.entry(c).or_insert(0.0) += 1.0;
+        counts
+    })
+}
@@ -0,0 +1,185 @@
+/// The cursor can not go further than this column, to not allocate a huge line for a malformed
+/// escape sequence.
+const MAX_COLUMN: usize = 1 << 16;
+
+/// The state of the escape sequence parser, following the state machine of the VT500 series
+/// (https://vt100.net/emu/dec_ansi_parser), restricted to what can happen on a single line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    CsiParam,
+    CsiIgnore,
+    /// OSC, DCS, SOS, PM and APC strings, terminated by BEL or ST (`ESC \\`)
+    String,
+}
+
+/// The content of a line of the terminal, with the position of the cursor.
+struct Screen {
+    /// `None` for the cells never written or erased, which are displayed as spaces only when
+    /// something is written after them
+    cells: Vec<Option<char>>,
+    cursor: usize,
+}
+
+impl Screen {
+    fn new() -> Self {
+        Self {
+            cells: vec![],
+            cursor: 0,
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        if self.cursor >= MAX_COLUMN {
+            return;
+        }
+        if self.cursor >= self.cells.len() {
+            self.cells.resize(self.cursor + 1, None);
+        }
+        self.cells[self.cursor] = Some(c);
+        self.cursor += 1;
+    }
+
+    fn erase(&mut self, from: usize, to: usize) {
+        let to = to.min(self.cells.len());
+        if from < to {
+            self.cells[from..to].fill(None);
+        }
+    }
+
+    fn execute_csi(&mut self, params: &[usize], action: char) {
+        // a missing or 0 parameter means 1 for the movements, and the parameters saturate
+        let n = params.first().copied().unwrap_or(0).clamp(1, MAX_COLUMN);
+        match action {
+            // cursor forward/backward
+            'C' | 'a' => self.cursor = self.cursor.saturating_add(n).min(MAX_COLUMN),
+            'D' => self.cursor = self.cursor.saturating_sub(n),
+            // cursor to column
+            'G' | '`' => self.cursor = n - 1,
+            // cursor position: only the column matters on a single line
+            'H' | 'f' => self.cursor = params.get(1).copied().unwrap_or(0).clamp(1, MAX_COLUMN) - 1,
+            // erase in line/display
+            'K' | 'J' => match params.first().copied().unwrap_or(0) {
+                0 => self.erase(self.cursor, usize::MAX),
+                1 => self.erase(0, self.cursor + 1),
+                _ => self.erase(0, usize::MAX),
+            },
+            // erase characters
+            'X' => self.erase(self.cursor, self.cursor.saturating_add(n)),
+            // delete characters
+            'P' if self.cursor < self.cells.len() => {
+                let end = self.cursor.saturating_add(n).min(self.cells.len());
+                self.cells.drain(self.cursor..end);
+            }
+            // insert blank characters
+            '@' if self.cursor < self.cells.len() => {
+                let n = n.min(MAX_COLUMN - self.cells.len());
+                self.cells
+                    .splice(self.cursor..self.cursor, std::iter::repeat_n(None, n));
+            }
+            // SGR (colors) and everything that moves to another line is ignored
+            _ => {}
+        }
+    }
+
+    fn text(&self) -> String {
+        let end = self
+            .cells
+            .iter()
+            .rposition(|c| c.is_some())
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        self.cells[..end].iter().map(|c| c.unwrap_or(' ')).collect()
+    }
+}
+
+/// Render a line of a log as a terminal would display it: the escape sequences (colors, cursor
+/// movements, erasures, OSC hyperlinks...) are interpreted and removed, and the text written over
+/// by `\r` or the cursor movements is replaced.
+pub fn render(line: &str) -> String {
+    let mut screen = Screen::new();
+    let mut state = State::Ground;
+    let mut params: Vec<usize> = vec![];
+    for c in line.chars() {
+        // the controls that are executed whatever the state
+        match c {
+            '\x18' | '\x1a' => {
+                state = State::Ground;
+                continue;
+            }
+            '\x1b' => {
+                state = State::Escape;
+                continue;
+            }
+            '\u{9b}' => {
+                params.clear();
+                state = State::CsiParam;
+                continue;
+            }
+            '\u{90}' | '\u{98}' | '\u{9d}' | '\u{9e}' | '\u{9f}' => {
+                state = State::String;
+                continue;
+            }
+            _ => {}
+        }
+        match state {
+            State::Ground => match c {
+                '\r' => screen.cursor = 0,
+                '\x08' => screen.cursor = screen.cursor.saturating_sub(1),
+                '\t' => screen.put(c),
+                c if c.is_control() => {}
+                c => screen.put(c),
+            },
+            State::Escape => match c {
+                '[' => {
+                    params.clear();
+                    state = State::CsiParam;
+                }
+                ']' | 'P' | 'X' | '^' | '_' => state = State::String,
+                '\x20'..='\x2f' => state = State::EscapeIntermediate,
+                _ => state = State::Ground,
+            },
+            State::EscapeIntermediate => {
+                if !('\x20'..='\x2f').contains(&c) {
+                    state = State::Ground;
+                }
+            }
+            State::CsiParam => match c {
+                '0'..='9' => {
+                    if params.is_empty() {
+                        params.push(0);
+                    }
+                    let last = params.last_mut().unwrap();
+                    *last = last.saturating_mul(10).saturating_add(c as usize - '0' as usize);
+                }
+                ';' | ':' => {
+                    if params.is_empty() {
+                        params.push(0);
+                    }
+                    params.push(0);
+                }
+                // private markers and intermediates are accepted but not used
+                '<'..='?' | '\x20'..='\x2f' => {}
+                '\x40'..='\x7e' => {
+                    screen.execute_csi(&params, c);
+                    state = State::Ground;
+                }
+                c if c.is_control() => {}
+                _ => state = State::CsiIgnore,
+            },
+            State::CsiIgnore => {
+                if ('\x40'..='\x7e').contains(&c) {
+                    state = State::Ground;
+                }
+            }
+            State::String => {
+                if c == '\x07' || c == '\u{9c}' {
+                    state = State::Ground;
+                }
+            }
+        }
+    }
+    screen.text()
+}
@@ -0,0 +1,628 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use cidiff_annotations::{atomic, Dataset, Log, LogParser, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseEventKind},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Style},
+    symbols::{border, scrollbar},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Scrollbar, ScrollbarState},
+    DefaultTerminal, Frame,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    apollo::{
+        state::FileChooser,
+        widget::{LogFileWdiget, VotesWidget},
+        ApolloArgs, State,
+    },
+    clipboard::Clipboard,
+    error::draw_error,
+    Annotations, LogAnnotation, Selection, WhatToDo,
+};
+
+/// The decision of the adjudicator on a line the annotators disagree on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision {
+    pub line: usize,
+    /// If the line is in the gold set
+    pub accepted: bool,
+    pub reason: String,
+}
+
+/// The gold set of a log, with the decisions it was made with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct GoldLog {
+    /// The lines selected by every annotator of the log, or accepted by the adjudicator
+    lines: Vec<usize>,
+    decisions: Vec<Decision>,
+}
+
+/// The annotators of a log: how many annotated it, and who selected each line.
+struct Votes {
+    annotators: usize,
+    lines: HashMap<usize, Vec<String>>,
+}
+
+impl Votes {
+    fn new(annotations: &BTreeMap<String, Annotations>, log_path: &str) -> Self {
+        let mut votes = Self {
+            annotators: 0,
+            lines: HashMap::new(),
+        };
+        for (annotator, annotations) in annotations {
+            if let Some(annotation) = annotations.get(log_path).filter(|a| a.is_annotated()) {
+                votes.annotators += 1;
+                for selection in &annotation.selections {
+                    votes
+                        .lines
+                        .entry(selection.line)
+                        .or_default()
+                        .push(annotator.clone());
+                }
+            }
+        }
+        votes
+    }
+
+    /// If some annotators selected the line, but not all of them.
+    fn contested(&self, line: usize) -> bool {
+        self.lines.get(&line).is_some_and(|v| v.len() < self.annotators)
+    }
+
+    fn gold(&self, decisions: &[Decision]) -> Vec<usize> {
+        let mut lines = self
+            .lines
+            .iter()
+            .filter(
+                |(line, voters)| match decisions.iter().find(|d| d.line == **line) {
+                    Some(decision) => decision.accepted,
+                    None => voters.len() == self.annotators,
+                },
+            )
+            .map(|(line, _)| *line)
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    /// If every contested line has a decision.
+    fn adjudicated(&self, decisions: &[Decision]) -> bool {
+        self.lines
+            .keys()
+            .filter(|line| self.contested(**line))
+            .all(|line| decisions.iter().any(|d| d.line == *line))
+    }
+}
+
+/// Read the decisions of a gold set, there is none yet if the file does not exist.
+pub fn load_decisions(filename: &str) -> Result<BTreeMap<String, Vec<Decision>>> {
+    let gold: BTreeMap<String, GoldLog> = match fs::read_to_string(filename) {
+        Ok(content) => toml::from_str(&content).with_context(|| format!("{} is corrupted", filename))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => return Err(e).with_context(|| format!("could not read {}", filename)),
+    };
+    Ok(gold
+        .into_iter()
+        .map(|(log_path, gold)| (log_path, gold.decisions))
+        .filter(|(_, decisions)| !decisions.is_empty())
+        .collect())
+}
+
+/// Write the gold set of every annotated log, with the decisions (and to a csv file with the same
+/// name, with the type `gold`, if asked).
+fn save_gold(
+    filename: &str,
+    annotations: &BTreeMap<String, Annotations>,
+    decisions: &BTreeMap<String, Vec<Decision>>,
+    to_csv: bool,
+) -> Result<()> {
+    let mut gold = BTreeMap::new();
+    for log_path in annotations.values().flat_map(|a| {
+        a.iter()
+            .filter(|(_, a)| a.is_annotated())
+            .map(|(log_path, _)| log_path)
+    }) {
+        let decisions = decisions.get(log_path).cloned().unwrap_or_default();
+        let lines = Votes::new(annotations, log_path).gold(&decisions);
+        gold.insert(log_path, GoldLog { lines, decisions });
+    }
+    atomic::write(filename, toml::to_string(&gold)?)?;
+    if to_csv {
+        let mut output = csv::WriterBuilder::new().from_writer(vec![]);
+        output.write_record(["path", "type", "line"])?;
+        for (log_path, gold) in &gold {
+            for n in &gold.lines {
+                output.write_record([log_path.to_string(), "gold".to_string(), n.to_string()])?;
+            }
+        }
+        atomic::write(Path::new(filename).with_extension("csv"), output.into_inner()?)?;
+    }
+    Ok(())
+}
+
+/// The decision being written by the adjudicator.
+struct Prompt {
+    accepted: bool,
+    reason: String,
+}
+
+/// A log opened to adjudicate the lines the annotators disagree on.
+pub struct FileAdjudicated {
+    /// The first displayed row
+    start: usize,
+    line_start: usize,
+    /// The highlighted row
+    highlighted: usize,
+    log_path: String,
+    log: Log,
+    /// Every line is displayed, the lines are not folded
+    rows: Vec<usize>,
+    votes: Votes,
+    decisions: Vec<Decision>,
+    prompt: Option<Prompt>,
+}
+
+impl FileAdjudicated {
+    pub fn new(
+        parser: &LogParser,
+        dataset: &Dataset,
+        log_path: String,
+        annotations: &BTreeMap<String, Annotations>,
+        decisions: Vec<Decision>,
+    ) -> Result<Self> {
+        let failure_log = dataset.failure_log(&log_path);
+        let log = parser
+            .parse_log_file(&failure_log)
+            .with_context(|| format!("could not read {}", failure_log.display()))?;
+        let rows = (0..log.lines.len()).collect();
+        let votes = Votes::new(annotations, &log_path);
+        Ok(Self {
+            start: 0,
+            line_start: 0,
+            highlighted: 0,
+            log_path,
+            log,
+            rows,
+            votes,
+            decisions,
+            prompt: None,
+        })
+    }
+
+    /// Highlight the next (or previous) contested line, if there is one.
+    fn jump_to_contested(&mut self, forward: bool) {
+        let contested = |row: &usize| self.votes.contested(self.rows[*row]);
+        let row = if forward {
+            (self.highlighted + 1..self.rows.len()).find(contested)
+        } else {
+            (0..self.highlighted).rev().find(contested)
+        };
+        if let Some(row) = row {
+            self.highlighted = row;
+        }
+    }
+
+    fn handle_prompt(&mut self, code: KeyCode) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Backspace => {
+                prompt.reason.pop();
+            }
+            KeyCode::Char(c) => prompt.reason.push(c),
+            KeyCode::Enter if !prompt.reason.trim().is_empty() => {
+                let line = self.rows[self.highlighted];
+                self.decisions.retain(|d| d.line != line);
+                self.decisions.push(Decision {
+                    line,
+                    accepted: prompt.accepted,
+                    reason: prompt.reason.trim().to_string(),
+                });
+                self.decisions.sort_by_key(|d| d.line);
+                self.prompt = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl State for FileAdjudicated {
+    fn handle_input(&mut self, area: Rect, e: &Event, _: &mut Clipboard) -> Result<WhatToDo> {
+        let line = self.rows.get(self.highlighted).copied().unwrap_or_default();
+        match e {
+            Event::Key(key) if key.kind == KeyEventKind::Press && self.prompt.is_some() => {
+                self.handle_prompt(key.code);
+            }
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('q') => return Ok(WhatToDo::ListDir),
+                KeyCode::Char('j') | KeyCode::Down => self.highlighted += 1,
+                KeyCode::Char('k') | KeyCode::Up => self.highlighted = self.highlighted.saturating_sub(1),
+                KeyCode::Char('l') => self.line_start += 1,
+                KeyCode::Char('L') => self.line_start += 10,
+                KeyCode::Char('h') => self.line_start = self.line_start.saturating_sub(1),
+                KeyCode::Char('H') => self.line_start = self.line_start.saturating_sub(10),
+                KeyCode::Char('d') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted += (area.height / 2) as usize;
+                }
+                KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
+                }
+                KeyCode::Char('g') => self.highlighted = 0,
+                KeyCode::Char('G') => self.highlighted = self.rows.len().saturating_sub(1),
+                KeyCode::Char(']') => self.jump_to_contested(true),
+                KeyCode::Char('[') => self.jump_to_contested(false),
+                KeyCode::Char(c @ ('a' | 'r')) => {
+                    if !self.votes.lines.contains_key(&line) {
+                        bail!(
+                            "no annotator selected the line {}, there is nothing to adjudicate",
+                            line
+                        );
+                    }
+                    self.prompt = Some(Prompt {
+                        accepted: c == 'a',
+                        reason: String::new(),
+                    });
+                }
+                KeyCode::Char('c') => self.decisions.retain(|d| d.line != line),
+                _ => (),
+            },
+            Event::Mouse(mouse) => match mouse.kind {
+                MouseEventKind::ScrollUp if self.start >= 2 => {
+                    self.start = self.start.saturating_sub(2);
+                    self.highlighted = self.highlighted.saturating_sub(2);
+                }
+                MouseEventKind::ScrollDown if self.start + 2 + area.height as usize - 4 < self.rows.len() => {
+                    self.start += 2;
+                    self.highlighted += 2;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        if self.highlighted >= self.rows.len() {
+            self.highlighted = self.rows.len().saturating_sub(1);
+        }
+        if self.start > self.highlighted {
+            self.start = self.highlighted;
+        }
+        if self.start + area.height as usize - 4 < self.highlighted {
+            self.start = self.highlighted - (area.height as usize - 4);
+        }
+        Ok(WhatToDo::StayOnSameState)
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let area = frame.area();
+
+        let widget_area = Rect::new(area.x, area.y, area.width, area.height - 3);
+        let votes = VotesWidget::new(
+            &self.rows,
+            self.votes.annotators,
+            &self.votes.lines,
+            &self.decisions,
+        )
+        .start(self.start)
+        .highlighted(self.highlighted);
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            // the names of many annotators are cut, they are all in the status
+            .constraints(vec![
+                Constraint::Length(votes.width().min(widget_area.width / 3)),
+                Constraint::Fill(1),
+            ])
+            .split(widget_area);
+        frame.render_widget(votes, layout[0]);
+        let gold = self
+            .votes
+            .gold(&self.decisions)
+            .into_iter()
+            .map(Selection::new)
+            .collect();
+        let widget = LogFileWdiget::new(&self.log.lines, &self.rows, Some(&gold))
+            .start(self.start)
+            .line_start(self.line_start)
+            .highlighted(self.highlighted);
+        frame.render_widget(widget, layout[1]);
+
+        let scrollbar = Scrollbar::new(ratatui::widgets::ScrollbarOrientation::VerticalRight)
+            .symbols(scrollbar::VERTICAL);
+        let mut scrollbar_state =
+            ScrollbarState::new(self.rows.len().saturating_sub(widget_area.height as usize))
+                .position(self.start);
+        frame.render_stateful_widget(
+            scrollbar,
+            widget_area.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+
+        let bottom_area = Rect::new(area.x, area.y + area.height - 3, area.width, 3);
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(bottom_area);
+
+        let line = self.rows.get(self.highlighted).copied().unwrap_or_default();
+        let voters = self
+            .votes
+            .lines
+            .get(&line)
+            .map(|v| v.join(", "))
+            .unwrap_or_default();
+        let decision = match self.decisions.iter().find(|d| d.line == line) {
+            Some(d) => format!(
+                " {}: {}",
+                if d.accepted { "accepted" } else { "rejected" },
+                d.reason
+            ),
+            None => String::new(),
+        };
+        let status_text = Line::from(vec![
+            Span::styled(&self.log_path[..], Style::default().fg(Color::Cyan)),
+            format!("  {}/{}  ", line, self.log.lines.len()).into(),
+            Span::styled(voters, Style::default().fg(Color::Yellow)),
+            decision.into(),
+        ]);
+        let status_block = Block::bordered().title("Status").border_set(border::THICK);
+        frame.render_widget(Paragraph::new(status_text).block(status_block), layout[0]);
+
+        let (title, instructions) = match &self.prompt {
+            Some(prompt) => (
+                if prompt.accepted {
+                    "Why is the line accepted?"
+                } else {
+                    "Why is the line rejected?"
+                },
+                Line::from(vec![
+                    Span::raw(&prompt.reason[..]),
+                    Span::styled("█", Style::default().fg(Color::Blue)),
+                    Span::raw(" | Confirm "),
+                    Span::styled("<Enter>", Style::default().fg(Color::Blue)),
+                    Span::raw(" | Cancel "),
+                    Span::styled("<Esc>", Style::default().fg(Color::Blue)),
+                ]),
+            ),
+            None => (
+                "Instructions",
+                Line::from(vec![
+                    Span::raw("Accept "),
+                    Span::styled("<a>", Style::default().fg(Color::Blue)),
+                    Span::raw(" | Reject "),
+                    Span::styled("<r>", Style::default().fg(Color::Blue)),
+                    Span::raw(" | Clear "),
+                    Span::styled("<c>", Style::default().fg(Color::Blue)),
+                    Span::raw(" | Contested line "),
+                    Span::styled("<[> ", Style::default().fg(Color::Blue)),
+                    Span::styled("<]>", Style::default().fg(Color::Blue)),
+                    Span::raw(" | Move "),
+                    Span::styled("<j> ", Style::default().fg(Color::Blue)),
+                    Span::styled("<k>", Style::default().fg(Color::Blue)),
+                    Span::raw(" | Return "),
+                    Span::styled("<q>", Style::default().fg(Color::Blue)),
+                ]),
+            ),
+        };
+        let instruction_block = Block::bordered().title(title).border_set(border::THICK);
+        let instruction_paragraph = Paragraph::new(instructions)
+            .block(instruction_block)
+            .alignment(Alignment::Center);
+        frame.render_widget(instruction_paragraph, layout[1]);
+    }
+
+    fn annotations(&self) -> Annotations {
+        HashMap::from([(
+            self.log_path.clone(),
+            LogAnnotation {
+                selections: self
+                    .votes
+                    .gold(&self.decisions)
+                    .into_iter()
+                    .map(Selection::new)
+                    .collect(),
+                ..Default::default()
+            },
+        )])
+    }
+}
+
+/// What is displayed: the list of logs, or a log being adjudicated.
+enum Screen<'a> {
+    List(FileChooser<'a>),
+    Log(FileAdjudicated),
+}
+
+/// The list of logs, where the logs with a decision for every contested line are marked.
+fn file_chooser<'a>(
+    log_paths: &'a Vec<&'a str>,
+    annotations: &BTreeMap<String, Annotations>,
+    decisions: &BTreeMap<String, Vec<Decision>>,
+) -> FileChooser<'a> {
+    let adjudicated = log_paths
+        .iter()
+        .filter_map(|log_path| {
+            let votes = Votes::new(annotations, log_path);
+            let decisions = decisions.get(*log_path).map(|d| &d[..]).unwrap_or_default();
+            (votes.annotators > 0 && votes.adjudicated(decisions)).then(|| {
+                let gold = LogAnnotation {
+                    selections: votes.gold(decisions).into_iter().map(Selection::new).collect(),
+                    ..Default::default()
+                };
+                (log_path.to_string(), gold)
+            })
+        })
+        .collect();
+    // the categories and notes are not part of the gold set
+    FileChooser::new(log_paths, adjudicated).read_only()
+}
+
+/// Run the adjudication TUI until the user exits. The gold set is saved after each decision.
+pub fn run(
+    mut terminal: DefaultTerminal,
+    dataset: &Dataset,
+    log_paths: Vec<PathBuf>,
+    parser: &LogParser,
+    args: &ApolloArgs,
+    annotations: BTreeMap<String, Annotations>,
+    mut decisions: BTreeMap<String, Vec<Decision>>,
+) -> Result<()> {
+    terminal.clear()?;
+    let mut log_paths = log_paths
+        .iter()
+        .map(|p| p.to_str().unwrap())
+        .collect::<Vec<&str>>();
+    log_paths.sort();
+    let mut clipboard = Clipboard::new();
+    let mut screen = Screen::List(file_chooser(&log_paths, &annotations, &decisions));
+    let mut last_position = (0, 0);
+    let mut error: Option<String> = None;
+    loop {
+        let completer_frames = terminal.draw(|frame| {
+            match &screen {
+                Screen::List(state) => state.draw(frame),
+                Screen::Log(state) => state.draw(frame),
+            }
+            if let Some(error) = &error {
+                draw_error(frame, error);
+            }
+        })?;
+        let area = completer_frames.area;
+        let e = event::read()?;
+        if let Event::Key(_) = e {
+            error = None;
+        }
+        let state: &mut dyn State = match &mut screen {
+            Screen::List(state) => state,
+            Screen::Log(state) => state,
+        };
+        let what_to_do = state.handle_input(area, &e, &mut clipboard).unwrap_or_else(|e| {
+            error = Some(format!("{:#}", e));
+            WhatToDo::StayOnSameState
+        });
+        if let Screen::Log(state) = &screen {
+            let previous = decisions.get(&state.log_path).map(|d| &d[..]).unwrap_or_default();
+            if previous != &state.decisions[..] {
+                decisions.insert(state.log_path.clone(), state.decisions.clone());
+                if let Err(e) = save_gold(&args.gold, &annotations, &decisions, args.to_csv) {
+                    error = Some(format!("{:#}", e));
+                }
+            }
+        }
+        match what_to_do {
+            WhatToDo::Exit => return save_gold(&args.gold, &annotations, &decisions, args.to_csv),
+            WhatToDo::StayOnSameState => {}
+            WhatToDo::OpenFile((start, path_index)) => {
+                let log_path = log_paths[path_index];
+                let opened = FileAdjudicated::new(
+                    parser,
+                    dataset,
+                    log_path.to_string(),
+                    &annotations,
+                    decisions.get(log_path).cloned().unwrap_or_default(),
+                );
+                match opened {
+                    Ok(opened) => {
+                        last_position = (start, path_index);
+                        screen = Screen::Log(opened);
+                    }
+                    Err(e) => error = Some(format!("{:#}", e)),
+                }
+            }
+            WhatToDo::ListDir => {
+                screen = Screen::List(
+                    file_chooser(&log_paths, &annotations, &decisions)
+                        .start(last_position.0)
+                        .highlighted(last_position.1),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The annotations of each annotator of a log, by the lines they selected (none when they did
+    /// not annotate it).
+    fn annotations(log_path: &str, annotators: &[(&str, Option<&[usize]>)]) -> BTreeMap<String, Annotations> {
+        annotators
+            .iter()
+            .map(|(annotator, lines)| {
+                let annotations = lines
+                    .map(|lines| {
+                        let annotation = LogAnnotation {
+                            selections: lines.iter().copied().map(Selection::new).collect(),
+                            note: "seen".to_string(),
+                            ..Default::default()
+                        };
+                        HashMap::from([(log_path.to_string(), annotation)])
+                    })
+                    .unwrap_or_default();
+                (annotator.to_string(), annotations)
+            })
+            .collect()
+    }
+
+    fn decision(line: usize, accepted: bool) -> Decision {
+        Decision {
+            line,
+            accepted,
+            reason: String::new(),
+        }
+    }
+
+    #[test]
+    fn the_gold_set_merges_the_agreement_and_the_decisions() {
+        let annotations = annotations(
+            "log",
+            &[
+                ("alice", Some(&[1, 2, 3])),
+                ("bob", Some(&[2, 3, 4])),
+                ("carol", None),
+            ],
+        );
+        let votes = Votes::new(&annotations, "log");
+        // carol did not annotate the log, so they do not vote
+        assert_eq!(votes.annotators, 2);
+        assert!(votes.contested(1) && votes.contested(4));
+        assert!(!votes.contested(2) && !votes.contested(5));
+
+        assert_eq!(votes.gold(&[]), vec![2, 3]);
+        assert!(!votes.adjudicated(&[]));
+        let decisions = [decision(1, true), decision(4, false)];
+        assert_eq!(votes.gold(&decisions), vec![1, 2, 3]);
+        assert!(votes.adjudicated(&decisions));
+        // a decision also overrides the agreement
+        assert_eq!(votes.gold(&[decision(2, false)]), vec![3]);
+    }
+
+    #[test]
+    fn the_decisions_are_saved_with_the_gold_set() {
+        let filename = std::env::temp_dir()
+            .join(format!("cidiff-gold-{}.toml", std::process::id()))
+            .display()
+            .to_string();
+        assert!(load_decisions(&filename).unwrap().is_empty());
+        let annotations = annotations("log", &[("alice", Some(&[1, 2])), ("bob", Some(&[2]))]);
+        let decisions = BTreeMap::from([("log".to_string(), vec![decision(1, false)])]);
+        save_gold(&filename, &annotations, &decisions, false).unwrap();
+
+        assert_eq!(load_decisions(&filename).unwrap(), decisions);
+        let gold: BTreeMap<String, GoldLog> =
+            toml::from_str(&fs::read_to_string(&filename).unwrap()).unwrap();
+        assert_eq!(gold["log"].lines, vec![2]);
+        fs::remove_file(&filename).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+use crate::Selection;
+
+/// The changes made to the selections of a log during the session, to undo and redo them.
+/// Each change is remembered by the selections before it.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Vec<Selection>>,
+    redo: Vec<Vec<Selection>>,
+}
+
+impl History {
+    /// Remember the selections before a change. The changes undone can not be redone anymore.
+    pub fn record(&mut self, before: Vec<Selection>) {
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    /// The selections before the last change, if there is one to undo.
+    pub fn undo(&mut self, current: Vec<Selection>) -> Option<Vec<Selection>> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// The selections after the last change undone, if there is one to redo.
+    pub fn redo(&mut self, current: Vec<Selection>) -> Option<Vec<Selection>> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selections(lines: &[usize]) -> Vec<Selection> {
+        lines.iter().copied().map(Selection::new).collect()
+    }
+
+    #[test]
+    fn changes_are_undone_and_redone_in_order() {
+        let mut history = History::default();
+        assert_eq!(history.undo(selections(&[])), None);
+        history.record(selections(&[]));
+        history.record(selections(&[1]));
+        let current = selections(&[1, 2]);
+
+        let current = history.undo(current).unwrap();
+        assert_eq!(current, selections(&[1]));
+        let current = history.undo(current).unwrap();
+        assert_eq!(current, selections(&[]));
+        assert_eq!(history.undo(current.clone()), None);
+
+        let current = history.redo(current).unwrap();
+        assert_eq!(current, selections(&[1]));
+        let current = history.redo(current).unwrap();
+        assert_eq!(current, selections(&[1, 2]));
+        assert_eq!(history.redo(current), None);
+    }
+
+    #[test]
+    fn a_new_change_can_not_be_redone_after() {
+        let mut history = History::default();
+        history.record(selections(&[]));
+        let current = history.undo(selections(&[1])).unwrap();
+        history.record(current);
+        assert_eq!(history.redo(selections(&[3])), None);
+        assert_eq!(history.undo(selections(&[3])), Some(selections(&[])));
+    }
+}
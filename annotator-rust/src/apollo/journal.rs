@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use cidiff_annotations::{atomic, Result};
+
+use crate::Annotations;
+
+/// How often the journal is written again when a change could not be written right away.
+pub const JOURNAL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A sidecar of the annotation file, holding the annotations of the running session.
+/// It is written each time the annotations change, and removed once they are saved, so it only
+/// exists when a session ended without saving (a crash, a killed terminal, a lost connection...).
+pub struct Journal {
+    path: PathBuf,
+    /// The annotations in the journal
+    written: Annotations,
+    /// If the last change could not be written
+    dirty: bool,
+}
+
+impl Journal {
+    /// The journal of an annotator, `annotations.toml.alice.journal` for the annotator `alice` of
+    /// `annotations.toml`.
+    pub fn new(output: &str, annotator: &str) -> Self {
+        Self {
+            path: PathBuf::from(format!("{}.{}.journal", output, annotator)),
+            written: HashMap::new(),
+            dirty: false,
+        }
+    }
+
+    /// Read the journal left by a previous session, with the time it was last written.
+    fn read(&self) -> Result<Option<(Annotations, DateTime<Local>)>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("could not read {}", self.path.display())),
+        };
+        let modified = fs::metadata(&self.path)?.modified()?;
+        let annotations =
+            toml::from_str(&content).with_context(|| format!("{} is corrupted", self.path.display()))?;
+        Ok(Some((annotations, modified.into())))
+    }
+
+    /// Offer to restore the annotations of the journal left by a previous session, if they differ
+    /// from the saved ones. It asks on the terminal, before the TUI starts.
+    /// The journal then holds the annotations the new session starts with.
+    pub fn restore(&mut self, annotations: Annotations) -> Result<()> {
+        self.written = self.ask(annotations)?;
+        Ok(())
+    }
+
+    /// The last annotations recorded.
+    pub fn annotations(&self) -> &Annotations {
+        &self.written
+    }
+
+    fn ask(&self, annotations: Annotations) -> Result<Annotations> {
+        let (journaled, modified) = match self.read() {
+            Ok(Some(journal)) => journal,
+            Ok(None) => return Ok(annotations),
+            Err(e) => {
+                // it is replaced at the first change
+                eprintln!("{:#}, it is ignored", e);
+                return Ok(annotations);
+            }
+        };
+        if journaled == annotations {
+            self.remove()?;
+            return Ok(annotations);
+        }
+        print!(
+            "{} holds the annotations of a session that was not saved ({} lines, last changed on {}), \
+            against {} lines saved. Restore them? [Y/n] ",
+            self.path.display(),
+            count_lines(&journaled),
+            modified.format("%Y-%m-%d %H:%M:%S"),
+            count_lines(&annotations),
+        );
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if matches!(answer.trim(), "" | "y" | "Y" | "yes") {
+            Ok(journaled)
+        } else {
+            self.remove()?;
+            Ok(annotations)
+        }
+    }
+
+    /// Write the annotations if they changed since the last write.
+    pub fn record(&mut self, annotations: Annotations) -> Result<()> {
+        if annotations == self.written {
+            return Ok(());
+        }
+        self.written = annotations;
+        self.flush()
+    }
+
+    /// Write the annotations again if the last change could not be written.
+    pub fn tick(&mut self) -> Result<()> {
+        if self.dirty {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.dirty = true;
+        atomic::write(&self.path, toml::to_string(&self.written)?).with_context(|| {
+            format!(
+                "the annotations could not be journaled in {}",
+                self.path.display()
+            )
+        })?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Remove the journal, once its annotations are saved.
+    pub fn remove(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("could not remove {}", self.path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The number of annotated lines, to describe a set of annotations to the user.
+fn count_lines(annotations: &Annotations) -> usize {
+    annotations.values().map(|a| a.selections.len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LogAnnotation, Selection};
+
+    /// An annotation file of the temporary directory, for the journals of a test.
+    fn output(name: &str) -> String {
+        let file = format!("cidiff-journal-{}-{}.toml", name, std::process::id());
+        std::env::temp_dir().join(file).display().to_string()
+    }
+
+    fn annotations(lines: &[usize]) -> Annotations {
+        HashMap::from([(
+            "owner/repo/run1-0".to_string(),
+            LogAnnotation {
+                selections: lines.iter().copied().map(Selection::new).collect(),
+                ..Default::default()
+            },
+        )])
+    }
+
+    #[test]
+    fn the_next_session_reads_the_annotations_recorded_before_a_crash() {
+        let output = output("crash");
+        let mut journal = Journal::new(&output, "alice");
+        journal.restore(annotations(&[])).unwrap();
+        assert_eq!(journal.annotations(), &annotations(&[]));
+        journal.record(annotations(&[1, 2])).unwrap();
+
+        let next = Journal::new(&output, "alice");
+        let (journaled, _) = next.read().unwrap().unwrap();
+        assert_eq!(journaled, annotations(&[1, 2]));
+        // each annotator has their own journal
+        assert!(Journal::new(&output, "bob").read().unwrap().is_none());
+
+        next.remove().unwrap();
+        assert!(next.read().unwrap().is_none());
+        next.remove().unwrap();
+    }
+
+    #[test]
+    fn a_journal_of_the_saved_annotations_is_removed_without_asking() {
+        let output = output("saved");
+        let mut journal = Journal::new(&output, "alice");
+        journal.record(annotations(&[3])).unwrap();
+
+        let mut next = Journal::new(&output, "alice");
+        next.restore(annotations(&[3])).unwrap();
+        assert_eq!(next.annotations(), &annotations(&[3]));
+        assert!(next.read().unwrap().is_none());
+    }
+
+    #[test]
+    fn a_corrupted_journal_is_replaced_at_the_first_change() {
+        let output = output("corrupted");
+        let mut journal = Journal::new(&output, "alice");
+        fs::write(&journal.path, "not a journal [").unwrap();
+        journal.restore(annotations(&[1])).unwrap();
+        assert_eq!(journal.annotations(), &annotations(&[1]));
+        assert!(journal.read().is_err());
+
+        journal.record(annotations(&[1, 4])).unwrap();
+        let (journaled, _) = journal.read().unwrap().unwrap();
+        assert_eq!(journaled, annotations(&[1, 4]));
+        journal.remove().unwrap();
+    }
+}
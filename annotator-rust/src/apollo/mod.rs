@@ -1,11 +1,23 @@
-use std::{collections::HashMap, io, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
 
 use crate::{
-    apollo::state::{FileChooser, FileOpened},
-    load_annotations, save_annotations, WhatToDo,
+    apollo::{
+        history::History,
+        journal::{Journal, JOURNAL_INTERVAL},
+        state::{FileChooser, FileOpened},
+    },
+    clipboard::Clipboard,
+    error::draw_error,
+    load_annotations, save_annotations, Annotations, WhatToDo,
 };
+use anyhow::Context;
+use chrono::Local;
+use cidiff_annotations::{Dataset, LogParser, Result};
 use clap::Args;
-use copypasta::ClipboardContext;
 use ratatui::{
     crossterm::{
         event::{
@@ -17,7 +29,12 @@ use ratatui::{
     DefaultTerminal, Frame,
 };
 
+mod adjudication;
+mod history;
+mod journal;
+mod notes;
 mod state;
+mod timer;
 mod widget;
 
 /// A tool to annotate a list of log files
@@ -29,67 +46,243 @@ pub struct ApolloArgs {
     /// If the program should output the annotations as a csv too. (using the `output` filename)
     #[arg(short, long)]
     to_csv: bool,
+    /// The identifier of the annotator, to keep the annotations of each annotator apart
+    #[arg(short, long, default_value_t = String::from("anonymous"), value_parser = parse_annotator)]
+    annotator: String,
+    /// Adjudicate the lines the annotators disagree on into a gold set, instead of annotating
+    #[arg(long)]
+    adjudicate: bool,
+    /// The toml file to save/load the gold set and the decisions of the adjudication to/from
+    #[arg(long, default_value_t = String::from("gold.toml"))]
+    gold: String,
+    /// The labels that can be given to a selected line, a new selection has the first one. Without
+    /// labels, a line is only selected or not
+    #[arg(long, value_delimiter = ',', default_value = "cause,context,noise")]
+    labels: Vec<String>,
+    /// The highest severity that can be given to a selected line (from 1), 0 to not give severities
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(0..=9))]
+    severities: u8,
+    /// The categories that can be given to the failure of a log
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "compilation,test,dependency,infrastructure,timeout,other"
+    )]
+    categories: Vec<String>,
+    /// Show the success log next to the failure log when a log is opened (toggled with `D`)
+    #[arg(long)]
+    side_by_side: bool,
 }
-pub fn bootstrap(args: ApolloArgs, dataset_path: &str, log_paths: Vec<PathBuf>) -> io::Result<()> {
-    let annotations = load_annotations(&args.output);
-    let mut terminal = ratatui::init();
-    execute!(std::io::stdout(), EnableFocusChange, EnableMouseCapture)?;
-    terminal.clear()?;
-    let app_result = run(terminal, dataset_path, log_paths, annotations);
-    execute!(std::io::stdout(), DisableFocusChange, DisableMouseCapture)?;
-    match app_result {
-        Ok(annotation) => {
-            save_annotations(&args.output, annotation, args.to_csv);
-            ratatui::restore();
-            Ok(())
+
+fn parse_annotator(annotator: &str) -> Result<String> {
+    if annotator.is_empty() || annotator.contains(|c: char| c.is_whitespace() || c == ':') {
+        anyhow::bail!("the identifier of an annotator can not be empty, nor contain spaces or `:`");
+    }
+    Ok(annotator.to_string())
+}
+pub fn bootstrap(
+    args: ApolloArgs,
+    dataset: &Dataset,
+    log_paths: Vec<PathBuf>,
+    parser: &LogParser,
+) -> Result<()> {
+    if args.adjudicate {
+        return bootstrap_adjudication(args, dataset, log_paths, parser);
+    }
+    let (annotations, error) = match load_annotations(&args.output, &args.annotator) {
+        Ok(mut annotations) => (annotations.remove(&args.annotator).unwrap_or_default(), None),
+        // a file that can not be parsed is moved aside to start a new one, what the humans did is
+        // kept in the backup; the other errors (e.g. the permissions) must be fixed first
+        Err(e) if e.downcast_ref::<toml::de::Error>().is_some() => {
+            let backup = format!("{}.{}.bak", args.output, Local::now().format("%Y-%m-%dT%H-%M-%S"));
+            if Path::new(&backup).exists() {
+                anyhow::bail!("{:#}, and {} already exists", e, backup);
+            }
+            fs::rename(&args.output, &backup)
+                .with_context(|| format!("{:#}, and it could not be moved to {}", e, backup))?;
+            (
+                HashMap::new(),
+                Some(format!("{:#} (it was moved to {})", e, backup)),
+            )
         }
-        Err(e) => Err(e),
+        Err(e) => return Err(e),
+    };
+    let mut journal = Journal::new(&args.output, &args.annotator);
+    journal.restore(annotations)?;
+    let terminal = ratatui::init();
+    let app_result = execute!(std::io::stdout(), EnableFocusChange, EnableMouseCapture)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| run(terminal, dataset, log_paths, parser, &args, journal, error));
+    let _ = execute!(std::io::stdout(), DisableFocusChange, DisableMouseCapture);
+    ratatui::restore();
+    app_result
+}
+
+fn bootstrap_adjudication(
+    args: ApolloArgs,
+    dataset: &Dataset,
+    log_paths: Vec<PathBuf>,
+    parser: &LogParser,
+) -> Result<()> {
+    let annotations = load_annotations(&args.output, &args.annotator)?;
+    if annotations.is_empty() {
+        anyhow::bail!("there is no annotation to adjudicate in {}", args.output);
     }
+    let decisions = adjudication::load_decisions(&args.gold)?;
+    let terminal = ratatui::init();
+    let app_result = execute!(std::io::stdout(), EnableFocusChange, EnableMouseCapture)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| {
+            adjudication::run(
+                terminal,
+                dataset,
+                log_paths,
+                parser,
+                &args,
+                annotations,
+                decisions,
+            )
+        });
+    let _ = execute!(std::io::stdout(), DisableFocusChange, DisableMouseCapture);
+    ratatui::restore();
+    app_result
 }
+
+/// Run the TUI until the user exits. The annotations are saved each time the user goes back to the
+/// list of files, and on exit. In between, each change is written to the journal.
 fn run(
     mut terminal: DefaultTerminal,
-    dataset_path: &str,
+    dataset: &Dataset,
     log_paths: Vec<PathBuf>,
-    annotations: HashMap<String, Vec<usize>>,
-) -> io::Result<HashMap<String, Vec<usize>>> {
+    parser: &LogParser,
+    args: &ApolloArgs,
+    mut journal: Journal,
+    mut error: Option<String>,
+) -> Result<()> {
+    terminal.clear()?;
     let mut log_paths = log_paths
         .iter()
         .map(|p| p.to_str().unwrap())
         .collect::<Vec<&str>>();
     log_paths.sort();
-    let mut clipboard = ClipboardContext::new().unwrap();
-    let mut state: Box<dyn State> = Box::new(FileChooser::new(&log_paths, annotations));
+    let mut clipboard = Clipboard::new();
+    // the progress of the other annotators, updated each time the annotations are saved
+    let mut progress = load_annotations(&args.output, &args.annotator)
+        .map(|all| others_progress(&all, &args.annotator))
+        .unwrap_or_default();
+    let mut state: Box<dyn State> = Box::new(
+        FileChooser::new(&log_paths, journal.annotations().clone())
+            .progress(progress.clone())
+            .categories(&args.categories),
+    );
     let mut last_position = (0, 0);
+    // the history of the changes of each log opened during the session
+    let mut histories: HashMap<String, History> = HashMap::new();
+    // whether the last exit failed to save the annotations
+    let mut exit_failed = false;
     loop {
         let completer_frames = terminal.draw(|frame| {
             state.draw(frame);
+            if let Some(error) = &error {
+                draw_error(frame, error);
+            }
         })?;
         let area = completer_frames.area;
+        if !event::poll(JOURNAL_INTERVAL)? {
+            if let Err(e) = journal.tick() {
+                error = Some(format!("{:#}", e));
+            }
+            continue;
+        }
         let e = event::read()?;
-        let what_to_do = state.handle_input(area, &e, &mut clipboard);
+        if let Event::Key(_) = e {
+            error = None;
+        }
+        let what_to_do = state.handle_input(area, &e, &mut clipboard).unwrap_or_else(|e| {
+            error = Some(format!("{:#}", e));
+            WhatToDo::StayOnSameState
+        });
+        if let Err(e) = journal.record(state.annotations()) {
+            error = Some(format!("{:#}", e));
+        }
         match what_to_do {
-            WhatToDo::Exit => return Ok(state.annotations()),
+            WhatToDo::Exit => {
+                match save_annotations(&args.output, &args.annotator, &state.annotations(), args.to_csv) {
+                    Ok(_) => return journal.remove(),
+                    Err(e) if exit_failed => return Err(e),
+                    Err(e) => {
+                        exit_failed = true;
+                        error = Some(format!("{:#} (press q again to quit without saving)", e));
+                        continue;
+                    }
+                }
+            }
             WhatToDo::StayOnSameState => {}
             WhatToDo::OpenFile((start, path_index)) => {
-                last_position = (start, path_index);
-                state = Box::new(FileOpened::new(
-                    dataset_path,
-                    log_paths[path_index].to_string(),
-                    state.annotations(),
-                ));
+                let log_path = log_paths[path_index];
+                let opened = FileOpened::new(parser, dataset, log_path.to_string(), state.annotations()).map(
+                    |opened| {
+                        opened
+                            .labels(&args.labels)
+                            .severities(args.severities)
+                            .categories(&args.categories)
+                            .side_by_side(args.side_by_side)
+                            .history(histories.remove(log_path).unwrap_or_default())
+                    },
+                );
+                match opened {
+                    Ok(opened) => {
+                        last_position = (start, path_index);
+                        state = Box::new(opened);
+                    }
+                    Err(e) => error = Some(format!("{:#}", e)),
+                }
             }
             WhatToDo::ListDir => {
+                if let Some((log_path, history)) = state.take_history() {
+                    histories.insert(log_path, history);
+                }
+                match save_annotations(&args.output, &args.annotator, &state.annotations(), args.to_csv) {
+                    Ok(all) => {
+                        progress = others_progress(&all, &args.annotator);
+                        if let Err(e) = journal.remove() {
+                            error = Some(format!("{:#}", e));
+                        }
+                    }
+                    Err(e) => error = Some(format!("{:#}", e)),
+                }
                 state = Box::new(
                     FileChooser::new(&log_paths, state.annotations())
                         .start(last_position.0)
-                        .highlighted(last_position.1),
+                        .highlighted(last_position.1)
+                        .progress(progress.clone())
+                        .categories(&args.categories),
                 );
             }
         }
+        exit_failed = false;
     }
 }
+
+/// The number of logs annotated by each of the other annotators.
+fn others_progress(all: &BTreeMap<String, Annotations>, annotator: &str) -> Vec<(String, usize)> {
+    all.iter()
+        .filter(|(other, _)| *other != annotator)
+        .map(|(other, annotations)| {
+            (
+                other.clone(),
+                annotations.values().filter(|a| a.is_annotated()).count(),
+            )
+        })
+        .collect()
+}
+
 trait State {
-    fn handle_input(&mut self, area: Rect, event: &Event, clipboard: &mut ClipboardContext) -> WhatToDo;
+    fn handle_input(&mut self, area: Rect, event: &Event, clipboard: &mut Clipboard) -> Result<WhatToDo>;
     fn draw(&self, frame: &mut Frame);
-    fn annotations(&self) -> HashMap<String, Vec<usize>>;
+    fn annotations(&self) -> Annotations;
+    /// The history of the changes of the opened log, to keep it when the log is closed.
+    fn take_history(&mut self) -> Option<(String, History)> {
+        None
+    }
 }
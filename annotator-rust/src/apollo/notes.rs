@@ -0,0 +1,69 @@
+use ratatui::{
+    crossterm::event::KeyCode,
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+use crate::{Annotations, LogAnnotation};
+
+/// The note of a log being typed by the annotator.
+pub struct NoteEditor {
+    note: String,
+}
+
+/// What the annotator did with the note being typed.
+pub enum NoteInput {
+    Typing,
+    Confirmed(String),
+    Cancelled,
+}
+
+impl NoteEditor {
+    pub fn new(note: &str) -> Self {
+        Self {
+            note: note.to_string(),
+        }
+    }
+
+    pub fn input(&mut self, code: KeyCode) -> NoteInput {
+        match code {
+            KeyCode::Esc => return NoteInput::Cancelled,
+            KeyCode::Enter => return NoteInput::Confirmed(self.note.trim().to_string()),
+            KeyCode::Backspace => {
+                self.note.pop();
+            }
+            KeyCode::Char(c) => self.note.push(c),
+            _ => (),
+        }
+        NoteInput::Typing
+    }
+
+    /// The note, to show in place of the instructions.
+    pub fn prompt(&self) -> Line<'_> {
+        Line::from(vec![
+            Span::raw(&self.note[..]),
+            Span::styled("█", Style::default().fg(Color::Blue)),
+            Span::raw(" | Confirm "),
+            Span::styled("<Enter>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Cancel "),
+            Span::styled("<Esc>", Style::default().fg(Color::Blue)),
+        ])
+    }
+}
+
+/// The category after the given one: each category in turn, then none.
+pub fn next_category(category: Option<&str>, categories: &[String]) -> Option<String> {
+    let next = category
+        .and_then(|c| categories.iter().position(|x| x == c))
+        .map_or(0, |i| i + 1);
+    categories.get(next).cloned()
+}
+
+/// Change the annotation of a log. The log is not annotated anymore if nothing is left in it.
+pub fn edit(annotations: &mut Annotations, log_path: &str, edit: impl FnOnce(&mut LogAnnotation)) {
+    let annotation = annotations.entry(log_path.to_string()).or_default();
+    edit(annotation);
+    if annotation.is_empty() {
+        annotations.remove(log_path);
+    }
+}
@@ -1,16 +1,20 @@
-use std::{collections::HashMap, fs, path::Path, usize};
-
 use crate::{
     apollo::{
+        history::History,
+        notes::{self, NoteEditor, NoteInput},
+        timer::Timer,
         widget::{LogFileWdiget, PathListWidget},
         State,
     },
-    parse_file, WhatToDo,
+    clipboard::Clipboard,
+    search::Search,
+    side_by_side::SideBySide,
+    Annotations, LogAnnotation, Selection, WhatToDo,
 };
-
-use copypasta::{ClipboardContext, ClipboardProvider};
+use anyhow::Context;
+use cidiff_annotations::{steps::Folds, Dataset, Log, LogParser, Result};
 use ratatui::{
-    crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers, MouseEventKind},
+    crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind},
     layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Style},
     symbols::{border, scrollbar},
@@ -23,28 +27,89 @@ pub struct FileChooser<'a> {
     start: usize,
     highlighted: usize,
     log_paths: &'a Vec<&'a str>,
-    annotations: HashMap<String, Vec<usize>>,
+    annotations: Annotations,
+    /// The number of logs annotated by each of the other annotators
+    progress: Vec<(String, usize)>,
+    /// The categories that can be given to the failure of a log
+    categories: Vec<String>,
+    /// The note of the highlighted log, while it is typed
+    note: Option<NoteEditor>,
+    /// If the category and the note of the logs can be edited
+    editable: bool,
 }
 
 pub struct FileOpened {
+    /// The first displayed row
     start: usize,
     line_start: usize,
+    /// The highlighted row
     highlighted: usize,
     log_path: String,
-    lines: Vec<String>,
-    annotations: HashMap<String, Vec<usize>>,
+    log: Log,
+    /// The sections folded by the user, and the lines displayed in each row
+    folds: Folds,
+    annotations: Annotations,
+    /// The labels that can be given to a selection
+    labels: Vec<String>,
+    /// The highest severity that can be given to a selection
+    severities: u8,
+    /// The row where the visual mode started: the range goes from it to the highlighted row
+    anchor: Option<usize>,
+    history: History,
+    search: Search,
+    side_by_side: SideBySide,
+    categories: Vec<String>,
+    /// The note of the log, while it is typed
+    note: Option<NoteEditor>,
+    timer: Timer,
+}
+
+/// What to do with the lines of the range of the visual mode.
+#[derive(Debug, Clone, Copy)]
+enum RangeAction {
+    Select,
+    Deselect,
+    Invert,
 }
 
 impl<'a> FileChooser<'a> {
-    pub fn new(log_paths: &'a Vec<&'a str>, annotations: HashMap<String, Vec<usize>>) -> Self {
+    pub fn new(log_paths: &'a Vec<&'a str>, annotations: Annotations) -> Self {
         Self {
             start: 0,
             highlighted: 0,
             log_paths,
             annotations,
+            progress: vec![],
+            categories: vec![],
+            note: None,
+            editable: true,
         }
     }
 
+    /// Only to choose a log, without the category nor the note.
+    pub fn read_only(mut self) -> Self {
+        self.editable = false;
+        self
+    }
+
+    pub fn progress(mut self, progress: Vec<(String, usize)>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn categories(mut self, categories: &[String]) -> Self {
+        self.categories = categories.to_vec();
+        self
+    }
+
+    /// The logs annotated by the annotator.
+    fn annotated(&self) -> impl Iterator<Item = &str> {
+        self.annotations
+            .iter()
+            .filter(|(_, a)| a.is_annotated())
+            .map(|(log_path, _)| &log_path[..])
+    }
+
     pub fn start(mut self, start: usize) -> Self {
         self.start = start;
         self
@@ -57,44 +122,59 @@ impl<'a> FileChooser<'a> {
 }
 
 impl<'a> State for FileChooser<'a> {
-    fn handle_input(&mut self, area: Rect, e: &Event, clipboard: &mut ClipboardContext) -> WhatToDo {
+    fn handle_input(&mut self, area: Rect, e: &Event, clipboard: &mut Clipboard) -> Result<WhatToDo> {
+        let log_path = self.log_paths[self.highlighted];
         match e {
+            Event::Key(key) if key.kind == KeyEventKind::Press && self.note.is_some() => {
+                match self.note.as_mut().map(|note| note.input(key.code)) {
+                    Some(NoteInput::Confirmed(note)) => {
+                        notes::edit(&mut self.annotations, log_path, |a| a.note = note);
+                        self.note = None;
+                    }
+                    Some(NoteInput::Cancelled) => self.note = None,
+                    _ => (),
+                }
+            }
             Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-                KeyCode::Char('q') => return WhatToDo::Exit,
+                KeyCode::Char('q') => return Ok(WhatToDo::Exit),
                 KeyCode::Char('j') => self.highlighted += 1,
                 KeyCode::Down => self.highlighted += 1,
                 KeyCode::Char('k') => self.highlighted = self.highlighted.saturating_sub(1),
                 KeyCode::Up => self.highlighted = self.highlighted.saturating_sub(1),
-                KeyCode::Char('d') => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        self.highlighted += (area.height / 2) as usize;
-                    }
+                KeyCode::Char('d') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted += (area.height / 2) as usize;
                 }
-                KeyCode::Char('u') => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
-                    }
+                KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
                 }
                 KeyCode::Char('g') => self.highlighted = 0,
                 KeyCode::Char('G') => self.highlighted = self.log_paths.len() - 1,
                 KeyCode::Char('y') => {
-                    let _ = clipboard.set_contents(self.log_paths[self.highlighted].to_string());
+                    clipboard.copy(log_path.to_string())?;
+                }
+                KeyCode::Char('c') if self.editable => {
+                    let categories = &self.categories;
+                    notes::edit(&mut self.annotations, log_path, |a| {
+                        a.category = notes::next_category(a.category.as_deref(), categories)
+                    });
                 }
-                KeyCode::Enter => return WhatToDo::OpenFile((self.start, self.highlighted)),
+                KeyCode::Char('e') if self.editable => {
+                    let note = self.annotations.get(log_path).map_or("", |a| &a.note[..]);
+                    self.note = Some(NoteEditor::new(note));
+                }
+                KeyCode::Enter => return Ok(WhatToDo::OpenFile((self.start, self.highlighted))),
                 _ => (),
             },
             Event::Mouse(mouse) => match mouse.kind {
-                MouseEventKind::ScrollUp => {
-                    if self.start >= 2 {
-                        self.start = self.start.saturating_sub(2);
-                        self.highlighted = self.highlighted.saturating_sub(2);
-                    }
+                MouseEventKind::ScrollUp if self.start >= 2 => {
+                    self.start = self.start.saturating_sub(2);
+                    self.highlighted = self.highlighted.saturating_sub(2);
                 }
-                MouseEventKind::ScrollDown => {
-                    if self.start + 2 + area.height as usize - 4 < self.log_paths.len() {
-                        self.start += 2;
-                        self.highlighted += 2;
-                    }
+                MouseEventKind::ScrollDown
+                    if self.start + 2 + area.height as usize - 4 < self.log_paths.len() =>
+                {
+                    self.start += 2;
+                    self.highlighted += 2;
                 }
                 _ => {}
             },
@@ -109,7 +189,7 @@ impl<'a> State for FileChooser<'a> {
         if self.start + area.height as usize - 4 < self.highlighted {
             self.start = self.highlighted - (area.height as usize - 4);
         }
-        WhatToDo::StayOnSameState
+        Ok(WhatToDo::StayOnSameState)
     }
 
     fn draw(&self, frame: &mut Frame) {
@@ -119,7 +199,13 @@ impl<'a> State for FileChooser<'a> {
         let files = PathListWidget::new(self.log_paths)
             .highlighted(self.highlighted)
             .start(self.start)
-            .annotated(self.annotations.keys().map(|s| &s[..]).collect());
+            .annotated(self.annotated().collect())
+            .tags(
+                self.annotations
+                    .iter()
+                    .map(|(log_path, a)| (&log_path[..], tag(a)))
+                    .collect(),
+            );
         frame.render_widget(files, files_areas);
 
         let scrollbar = Scrollbar::new(ratatui::widgets::ScrollbarOrientation::VerticalRight)
@@ -137,24 +223,34 @@ impl<'a> State for FileChooser<'a> {
         );
 
         let bottom_area = Rect::new(area.x, area.y + area.height - 3, area.width, 3);
+        let total = self.log_paths.len();
+        let completion_style = |n: usize| {
+            let color = if n * 4 < total {
+                Color::Red
+            } else if n * 2 < total {
+                Color::Yellow
+            } else if n * 4 < total * 3 {
+                Color::Cyan
+            } else {
+                Color::Green
+            };
+            Style::default().fg(color)
+        };
+        let n = self.annotated().count();
+        let mut completion = vec![Span::styled(format!("{}/{}", n, total), completion_style(n))];
+        for (annotator, n) in &self.progress {
+            completion.push(Span::raw(format!(" | {} ", annotator)));
+            completion.push(Span::styled(format!("{}/{}", n, total), completion_style(*n)));
+        }
+        let completion = Line::from(completion);
         let layout = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints(vec![Constraint::Percentage(15), Constraint::Percentage(85)])
+            .constraints(vec![
+                // wide enough for the progress of every annotator
+                Constraint::Length((completion.width() as u16 + 2).max(bottom_area.width * 15 / 100)),
+                Constraint::Fill(1),
+            ])
             .split(bottom_area);
-        let n = self.annotations.keys().len();
-        let color = if n < 25 {
-            Color::Red
-        } else if n < 50 {
-            Color::Yellow
-        } else if n < 75 {
-            Color::Cyan
-        } else {
-            Color::Green
-        };
-        let completion = Line::from(vec![Span::styled(
-            format!("{}/100", n),
-            Style::default().fg(color),
-        )]);
         let completion_paragraph = Paragraph::new(completion)
             .block(
                 Block::bordered()
@@ -162,9 +258,27 @@ impl<'a> State for FileChooser<'a> {
                     .border_set(border::THICK),
             )
             .alignment(Alignment::Center);
-        let instructions = Line::from(vec![
+        if let Some(note) = &self.note {
+            let note_block = Block::bordered()
+                .title(Title::from("Note").alignment(Alignment::Center))
+                .border_set(border::THICK);
+            frame.render_widget(completion_paragraph, layout[0]);
+            frame.render_widget(Paragraph::new(note.prompt()).block(note_block), layout[1]);
+            return;
+        }
+        let mut instructions = vec![
             Span::raw("Open File "),
             Span::styled("<Enter>", Style::default().fg(Color::Blue)),
+        ];
+        if self.editable {
+            instructions.extend([
+                Span::raw(" | Category "),
+                Span::styled("<c>", Style::default().fg(Color::Blue)),
+                Span::raw(" | Note "),
+                Span::styled("<e>", Style::default().fg(Color::Blue)),
+            ]);
+        }
+        instructions.extend([
             Span::raw(" | Move "),
             Span::styled("<j> ", Style::default().fg(Color::Blue)),
             Span::styled("<k>", Style::default().fg(Color::Blue)),
@@ -175,6 +289,7 @@ impl<'a> State for FileChooser<'a> {
             Span::raw(" | Exit "),
             Span::styled("<q>", Style::default().fg(Color::Blue)),
         ]);
+        let instructions = Line::from(instructions);
         let instruction_block = Block::bordered()
             .title(Title::from("Instructions").alignment(Alignment::Center))
             .border_set(border::THICK);
@@ -185,63 +300,216 @@ impl<'a> State for FileChooser<'a> {
         frame.render_widget(instruction_paragraph, layout[1]);
     }
 
-    fn annotations(&self) -> HashMap<String, Vec<usize>> {
+    fn annotations(&self) -> Annotations {
         self.annotations.clone()
     }
 }
 
 impl FileOpened {
-    pub fn new(dataset_path: &str, log_path: String, annotations: HashMap<String, Vec<usize>>) -> Self {
-        let lines = fs::read_to_string(Path::new(dataset_path).join(&log_path).join("failure.log"))
-            .map(parse_file)
-            .unwrap_or_default();
-        Self {
+    pub fn new(
+        parser: &LogParser,
+        dataset: &Dataset,
+        log_path: String,
+        annotations: Annotations,
+    ) -> Result<Self> {
+        let failure_log = dataset.failure_log(&log_path);
+        let log = parser
+            .parse_log_file(&failure_log)
+            .with_context(|| format!("could not read {}", failure_log.display()))?;
+        let folds = Folds::new(&log);
+        Ok(Self {
             start: 0,
             line_start: 0,
             highlighted: 0,
+            side_by_side: SideBySide::new(parser, dataset, &log_path),
             log_path,
-            lines,
+            log,
+            folds,
             annotations,
+            labels: vec![],
+            severities: 0,
+            anchor: None,
+            history: History::default(),
+            search: Search::default(),
+            categories: vec![],
+            note: None,
+            timer: Timer::start(),
+        })
+    }
+
+    pub fn labels(mut self, labels: &[String]) -> Self {
+        self.labels = labels.iter().filter(|l| !l.is_empty()).cloned().collect();
+        self
+    }
+
+    pub fn severities(mut self, severities: u8) -> Self {
+        self.severities = severities;
+        self
+    }
+
+    pub fn history(mut self, history: History) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn categories(mut self, categories: &[String]) -> Self {
+        self.categories = categories.to_vec();
+        self
+    }
+
+    /// Show the success log next to the failure log.
+    pub fn side_by_side(mut self, shown: bool) -> Self {
+        if shown != self.side_by_side.is_shown() {
+            self.side_by_side.toggle(&self.log.lines);
+        }
+        self
+    }
+
+    /// The annotation of the log, created if needed.
+    fn annotation(&mut self) -> &mut LogAnnotation {
+        self.annotations.entry(self.log_path.clone()).or_default()
+    }
+
+    /// The selections of the log.
+    fn selections(&self) -> Vec<Selection> {
+        self.annotations
+            .get(&self.log_path)
+            .map(|a| a.selections.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the selections of the log, e.g. by the ones of the history.
+    fn set_selections(&mut self, selections: Vec<Selection>) {
+        self.annotation().selections = selections;
+        if self.annotations[&self.log_path].is_empty() {
+            self.annotations.remove(&self.log_path);
+        }
+    }
+
+    /// Select or deselect a line. A new selection has the first label.
+    fn toggle(&mut self, line: usize) {
+        let label = self.labels.first().cloned();
+        let selections = &mut self.annotation().selections;
+        match selections.iter().position(|s| s.line == line) {
+            Some(i) => {
+                selections.remove(i);
+            }
+            None => selections.push(Selection {
+                label,
+                ..Selection::new(line)
+            }),
+        }
+    }
+
+    /// The selection of a line, which is selected if it is not.
+    fn selection(&mut self, line: usize) -> &mut Selection {
+        if !self.selected(line) {
+            self.toggle(line);
+        }
+        let selections = &mut self.annotation().selections;
+        selections.iter_mut().find(|s| s.line == line).unwrap()
+    }
+
+    fn selected(&self, line: usize) -> bool {
+        self.annotations
+            .get(&self.log_path)
+            .is_some_and(|annotation| annotation.selections.iter().any(|s| s.line == line))
+    }
+
+    /// Give the next label to a line (selecting it if needed).
+    fn cycle_label(&mut self, line: usize) {
+        let labels = self.labels.clone();
+        if labels.is_empty() {
+            return;
+        }
+        let selection = self.selection(line);
+        let next = match selection
+            .label
+            .as_ref()
+            .and_then(|l| labels.iter().position(|x| x == l))
+        {
+            Some(i) => (i + 1) % labels.len(),
+            None => 0,
+        };
+        selection.label = Some(labels[next].clone());
+    }
+
+    /// The first and last rows of the range of the visual mode, if it is on.
+    fn visual_range(&self) -> Option<(usize, usize)> {
+        self.anchor
+            .map(|anchor| (anchor.min(self.highlighted), anchor.max(self.highlighted)))
+    }
+
+    /// Apply the action to the lines displayed in the range of the visual mode (the lines hidden in
+    /// a folded section are left as they are), and leave the visual mode.
+    fn apply_to_range(&mut self, action: RangeAction) {
+        let Some((first, last)) = self.visual_range() else {
+            return;
+        };
+        for row in first..=last.min(self.folds.rows().len().saturating_sub(1)) {
+            let line = self.folds.rows()[row];
+            match (action, self.selected(line)) {
+                (RangeAction::Select, false) | (RangeAction::Deselect, true) | (RangeAction::Invert, _) => {
+                    self.toggle(line)
+                }
+                _ => (),
+            }
+        }
+        self.anchor = None;
+    }
+
+    /// Select every displayed line matching the search.
+    fn select_matches(&mut self) {
+        for row in 0..self.folds.rows().len() {
+            let line = self.folds.rows()[row];
+            if self.search.matches(&self.log.lines[line]) && !self.selected(line) {
+                self.toggle(line);
+            }
         }
     }
 }
 
 impl State for FileOpened {
-    fn handle_input(&mut self, area: Rect, e: &Event, _: &mut ClipboardContext) -> WhatToDo {
-        let mut toggle = || {
-            self.annotations
-                .entry(self.log_path.clone())
-                .and_modify(|v| {
-                    if v.contains(&self.highlighted) {
-                        v.remove(v.iter().position(|x| x == &self.highlighted).unwrap());
-                    } else {
-                        v.push(self.highlighted)
-                    }
-                })
-                .or_insert(vec![self.highlighted]);
-        };
+    fn handle_input(&mut self, area: Rect, e: &Event, _: &mut Clipboard) -> Result<WhatToDo> {
+        let line = self
+            .folds
+            .rows()
+            .get(self.highlighted)
+            .copied()
+            .unwrap_or_default();
+        self.timer.event(e);
+        // every change of the selections goes to the history, except the ones coming from it
+        let before = self.selections();
+        let mut from_history = false;
         match e {
+            Event::Key(key) if key.kind == KeyEventKind::Press && self.search.is_typing() => {
+                self.highlighted = self.search.input(key.code, &self.log.lines, self.folds.rows())?;
+            }
+            Event::Key(key) if key.kind == KeyEventKind::Press && self.note.is_some() => {
+                match self.note.as_mut().map(|note| note.input(key.code)) {
+                    Some(NoteInput::Confirmed(note)) => {
+                        notes::edit(&mut self.annotations, &self.log_path, |a| a.note = note);
+                        self.note = None;
+                    }
+                    Some(NoteInput::Cancelled) => self.note = None,
+                    _ => (),
+                }
+            }
             Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
                 KeyCode::Char('q') => {
-                    if self
-                        .annotations
-                        .get(&self.log_path)
-                        .map(|v| v.is_empty())
-                        .unwrap_or(false)
-                    {
-                        self.annotations.remove(&self.log_path);
-                    }
-                    return WhatToDo::ListDir;
+                    let session = self.timer.stop();
+                    self.annotation().sessions.push(session);
+                    return Ok(WhatToDo::ListDir);
                 }
                 KeyCode::Char('j') | KeyCode::Down => self.highlighted += 1,
                 KeyCode::Char('J') => {
-                    toggle();
+                    self.toggle(line);
                     self.highlighted += 1;
                     self.start += 1;
                 }
                 KeyCode::Char('k') | KeyCode::Up => self.highlighted = self.highlighted.saturating_sub(1),
                 KeyCode::Char('K') => {
-                    toggle();
+                    self.toggle(line);
                     self.highlighted = self.highlighted.saturating_sub(1);
                     self.start = self.start.saturating_sub(1);
                 }
@@ -249,51 +517,98 @@ impl State for FileOpened {
                 KeyCode::Char('L') => self.line_start += 10,
                 KeyCode::Char('h') => self.line_start = self.line_start.saturating_sub(1),
                 KeyCode::Char('H') => self.line_start = self.line_start.saturating_sub(10),
-                KeyCode::Char('d') => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        self.highlighted += (area.height / 2) as usize;
+                KeyCode::Char('d') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted += (area.height / 2) as usize;
+                }
+                KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
+                }
+                KeyCode::Char('g') => self.highlighted = 0,
+                KeyCode::Char('G') => self.highlighted = self.folds.rows().len().saturating_sub(1),
+                KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+                    if let Some(next) = self.history.redo(before.clone()) {
+                        self.set_selections(next);
                     }
+                    from_history = true;
                 }
                 KeyCode::Char('u') => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
+                    if let Some(previous) = self.history.undo(before.clone()) {
+                        self.set_selections(previous);
                     }
+                    from_history = true;
                 }
-                KeyCode::Char('g') => self.highlighted = 0,
-                KeyCode::Char('G') => self.highlighted = self.lines.len() - 1,
-                KeyCode::Char(' ') => {
-                    self.annotations
-                        .entry(self.log_path.clone())
-                        .and_modify(|v| {
-                            if v.contains(&self.highlighted) {
-                                v.remove(v.iter().position(|x| x == &self.highlighted).unwrap());
-                            } else {
-                                v.push(self.highlighted)
-                            }
-                        })
-                        .or_insert(vec![self.highlighted]);
+                KeyCode::Char('D') => self.side_by_side.toggle(&self.log.lines),
+                KeyCode::Char('c') => {
+                    let categories = &self.categories;
+                    notes::edit(&mut self.annotations, &self.log_path, |a| {
+                        a.category = notes::next_category(a.category.as_deref(), categories)
+                    });
+                }
+                KeyCode::Char('e') => {
+                    let note = self.annotations.get(&self.log_path).map_or("", |a| &a.note[..]);
+                    self.note = Some(NoteEditor::new(note));
+                }
+                KeyCode::Char('/') => self.search.start(true, self.highlighted),
+                KeyCode::Char('?') => self.search.start(false, self.highlighted),
+                KeyCode::Char(c @ ('n' | 'N')) => {
+                    if let Some(row) =
+                        self.search
+                            .next(&self.log.lines, self.folds.rows(), self.highlighted, c == 'N')
+                    {
+                        self.highlighted = row;
+                    }
                 }
+                KeyCode::Char('S') => self.select_matches(),
+                KeyCode::Char('v') if self.anchor.is_some() => self.anchor = None,
+                KeyCode::Char('v') => self.anchor = Some(self.highlighted),
+                KeyCode::Esc => self.anchor = None,
+                KeyCode::Char('s') if self.anchor.is_some() => self.apply_to_range(RangeAction::Select),
+                KeyCode::Char('d') if self.anchor.is_some() => self.apply_to_range(RangeAction::Deselect),
+                KeyCode::Char(' ') if self.anchor.is_some() => self.apply_to_range(RangeAction::Invert),
+                KeyCode::Char(' ') => self.toggle(line),
+                KeyCode::Char('t') => self.cycle_label(line),
+                KeyCode::Char(c @ '1'..='9') if (c as u8 - b'0') <= self.severities => {
+                    self.selection(line).severity = Some(c as u8 - b'0');
+                }
+                KeyCode::Char('z') => self.highlighted = self.folds.toggle(&self.log, self.highlighted),
+                KeyCode::Char('Z') => self.highlighted = self.folds.toggle_all(&self.log, self.highlighted),
                 _ => (),
             },
             Event::Mouse(mouse) => match mouse.kind {
-                MouseEventKind::ScrollUp => {
-                    if self.start >= 2 {
-                        self.start = self.start.saturating_sub(2);
-                        self.highlighted = self.highlighted.saturating_sub(2);
-                    }
+                MouseEventKind::ScrollUp if self.start >= 2 => {
+                    self.start = self.start.saturating_sub(2);
+                    self.highlighted = self.highlighted.saturating_sub(2);
                 }
-                MouseEventKind::ScrollDown => {
-                    if self.start + 2 + area.height as usize - 4 < self.lines.len() {
-                        self.start += 2;
-                        self.highlighted += 2;
-                    }
+                MouseEventKind::ScrollDown
+                    if self.start + 2 + area.height as usize - 4 < self.folds.rows().len() =>
+                {
+                    self.start += 2;
+                    self.highlighted += 2;
+                }
+                // dragging starts a range in visual mode, a click only highlights the row
+                MouseEventKind::Down(MouseButton::Left)
+                    if mouse.row < area.y + area.height - 3
+                        && mouse.column >= self.side_by_side.split(area).1.x =>
+                {
+                    self.highlighted = self.start + mouse.row.saturating_sub(area.y) as usize;
+                    self.anchor = Some(self.highlighted.min(self.folds.rows().len().saturating_sub(1)));
+                }
+                // dragging below the log scrolls it
+                MouseEventKind::Drag(MouseButton::Left) if self.anchor.is_some() => {
+                    self.highlighted = self.start + mouse.row.saturating_sub(area.y) as usize;
+                }
+                MouseEventKind::Up(MouseButton::Left) if self.anchor == Some(self.highlighted) => {
+                    self.anchor = None;
                 }
                 _ => {}
             },
             _ => {}
         }
-        if self.highlighted >= self.lines.len() {
-            self.highlighted = self.lines.len() - 1;
+        if !from_history && self.selections() != before {
+            self.history.record(before);
+        }
+        if self.highlighted >= self.folds.rows().len() {
+            self.highlighted = self.folds.rows().len().saturating_sub(1);
         }
         if self.start > self.highlighted {
             self.start = self.highlighted;
@@ -301,25 +616,49 @@ impl State for FileOpened {
         if self.start + area.height as usize - 4 < self.highlighted {
             self.start = self.highlighted - (area.height as usize - 4);
         }
-        WhatToDo::StayOnSameState
+        Ok(WhatToDo::StayOnSameState)
     }
 
     fn draw(&self, frame: &mut Frame) {
         let area = frame.area();
+        let line = self
+            .folds
+            .rows()
+            .get(self.highlighted)
+            .copied()
+            .unwrap_or_default();
 
-        let widget_area = Rect::new(area.x, area.y, area.width, area.height - 3);
-        let widget = LogFileWdiget::new(&self.lines, self.annotations.get(&self.log_path))
-            .start(self.start)
-            .line_start(self.line_start)
-            .highlighted(self.highlighted);
+        let (success_area, widget_area) =
+            self.side_by_side
+                .split(Rect::new(area.x, area.y, area.width, area.height - 3));
+        if let Some(success_area) = success_area {
+            let widget = self
+                .side_by_side
+                .widget(line, self.highlighted - self.start, self.line_start);
+            frame.render_widget(widget, success_area);
+        }
+        let widget = LogFileWdiget::new(
+            &self.log.lines,
+            self.folds.rows(),
+            self.annotations.get(&self.log_path).map(|a| &a.selections),
+        )
+        .start(self.start)
+        .line_start(self.line_start)
+        .highlighted(self.highlighted)
+        .visual(self.visual_range())
+        .search(self.search.regex());
 
         frame.render_widget(widget, widget_area);
 
         let scrollbar = Scrollbar::new(ratatui::widgets::ScrollbarOrientation::VerticalRight)
             .symbols(scrollbar::VERTICAL);
-        let mut scrollbar_state =
-            ScrollbarState::new(self.lines.len().saturating_sub(widget_area.height as usize))
-                .position(self.start);
+        let mut scrollbar_state = ScrollbarState::new(
+            self.folds
+                .rows()
+                .len()
+                .saturating_sub(widget_area.height as usize),
+        )
+        .position(self.start);
         frame.render_stateful_widget(
             scrollbar,
             widget_area.inner(Margin {
@@ -336,19 +675,113 @@ impl State for FileOpened {
             .split(bottom_area);
 
         let status_block = Block::bordered().title("Status").border_set(border::THICK);
+        let selection = self
+            .annotations
+            .get(&self.log_path)
+            .and_then(|annotation| annotation.selections.iter().find(|s| s.line == line));
         let status_text = Line::from(vec![
+            match self.visual_range() {
+                Some((first, last)) => Span::styled(
+                    format!("VISUAL {} rows  ", last - first + 1),
+                    Style::default().fg(Color::Yellow),
+                ),
+                None => Span::raw(""),
+            },
             Span::styled(&self.log_path[..], Style::default().fg(Color::Cyan)),
             "  ".into(),
-            format!("{}", self.highlighted).into(),
+            format!("{}", line).into(),
             "/".into(),
-            format!("{}", self.lines.len()).into(),
+            format!("{}", self.log.lines.len()).into(),
+            Span::styled(
+                match selection {
+                    Some(Selection { label, severity, .. }) => format!(
+                        "  {}{}",
+                        label.as_deref().unwrap_or(""),
+                        severity
+                            .map(|s| format!(" ({}/{})", s, self.severities))
+                            .unwrap_or_default()
+                    ),
+                    None => String::new(),
+                },
+                Style::default().fg(Color::Green),
+            ),
+            Span::styled(
+                self.annotations.get(&self.log_path).map(tag).unwrap_or_default(),
+                Style::default().fg(Color::Magenta),
+            ),
         ]);
         let status = Paragraph::new(status_text).block(status_block);
         frame.render_widget(status, layout[0]);
 
-        let instructions = Line::from(vec![
+        if let Some(note) = &self.note {
+            let note_block = Block::bordered().title("Note").border_set(border::THICK);
+            frame.render_widget(Paragraph::new(note.prompt()).block(note_block), layout[1]);
+            return;
+        }
+        if let Some(prompt) = self.search.prompt(&self.log.lines, self.folds.rows()) {
+            let instruction_block = Block::bordered().title("Search").border_set(border::THICK);
+            frame.render_widget(Paragraph::new(prompt).block(instruction_block), layout[1]);
+            return;
+        }
+        if self.anchor.is_some() {
+            let instructions = Line::from(vec![
+                Span::raw("Select range "),
+                Span::styled("<s>", Style::default().fg(Color::Blue)),
+                Span::raw(" | Deselect range "),
+                Span::styled("<d>", Style::default().fg(Color::Blue)),
+                Span::raw(" | Invert range "),
+                Span::styled("<Space>", Style::default().fg(Color::Blue)),
+                Span::raw(" | Extend "),
+                Span::styled("<j> ", Style::default().fg(Color::Blue)),
+                Span::styled("<k> ", Style::default().fg(Color::Blue)),
+                Span::styled("<g> ", Style::default().fg(Color::Blue)),
+                Span::styled("<G>", Style::default().fg(Color::Blue)),
+                Span::raw(" | Cancel "),
+                Span::styled("<v> ", Style::default().fg(Color::Blue)),
+                Span::styled("<Esc>", Style::default().fg(Color::Blue)),
+            ]);
+            let instruction_block = Block::bordered().title("Instructions").border_set(border::THICK);
+            let instruction_paragraph = Paragraph::new(instructions)
+                .block(instruction_block)
+                .alignment(Alignment::Center);
+            frame.render_widget(instruction_paragraph, layout[1]);
+            return;
+        }
+        let mut instructions = vec![
             Span::raw("Toggle line "),
             Span::styled("<Space>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Visual "),
+            Span::styled("<v>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Search "),
+            Span::styled("</> ", Style::default().fg(Color::Blue)),
+            Span::styled("<?> ", Style::default().fg(Color::Blue)),
+            Span::styled("<n> ", Style::default().fg(Color::Blue)),
+            Span::styled("<N>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Category "),
+            Span::styled("<c>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Note "),
+            Span::styled("<e>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Success log "),
+            Span::styled("<D>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Select matches "),
+            Span::styled("<S>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Undo "),
+            Span::styled("<u>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Redo "),
+            Span::styled("<C-r>", Style::default().fg(Color::Blue)),
+        ];
+        if !self.labels.is_empty() {
+            instructions.push(Span::raw(" | Label "));
+            instructions.push(Span::styled("<t>", Style::default().fg(Color::Blue)));
+        }
+        if self.severities > 0 {
+            instructions.push(Span::raw(" | Severity "));
+            instructions.push(Span::styled(
+                format!("<1-{}>", self.severities),
+                Style::default().fg(Color::Blue),
+            ));
+        }
+        instructions.extend([
             Span::raw(" | Move "),
             Span::styled("<h> ", Style::default().fg(Color::Blue)),
             Span::styled("<j> ", Style::default().fg(Color::Blue)),
@@ -357,6 +790,9 @@ impl State for FileOpened {
             Span::raw(" | Toggle & move "),
             Span::styled("<J> ", Style::default().fg(Color::Blue)),
             Span::styled("<K> ", Style::default().fg(Color::Blue)),
+            Span::raw(" | Fold step "),
+            Span::styled("<z> ", Style::default().fg(Color::Blue)),
+            Span::styled("<Z>", Style::default().fg(Color::Blue)),
             Span::raw(" | Top "),
             Span::styled("<g>", Style::default().fg(Color::Blue)),
             Span::raw(" | Bottom "),
@@ -365,12 +801,92 @@ impl State for FileOpened {
             Span::styled("<q>", Style::default().fg(Color::Blue)),
         ]);
         let instruction_block = Block::bordered().title("Instructions").border_set(border::THICK);
-        let instruction_paragraph = Paragraph::new(instructions)
+        let instruction_paragraph = Paragraph::new(Line::from(instructions))
             .block(instruction_block)
             .alignment(Alignment::Center);
         frame.render_widget(instruction_paragraph, layout[1]);
     }
-    fn annotations(&self) -> HashMap<String, Vec<usize>> {
+    fn annotations(&self) -> Annotations {
         self.annotations.clone()
     }
+
+    fn take_history(&mut self) -> Option<(String, History)> {
+        Some((self.log_path.clone(), std::mem::take(&mut self.history)))
+    }
+}
+
+/// The category of a log and whether it has a note, shown after its path.
+fn tag(annotation: &LogAnnotation) -> String {
+    let category = annotation
+        .category
+        .as_ref()
+        .map(|c| format!(" [{}]", c))
+        .unwrap_or_default();
+    let note = if annotation.note.is_empty() { "" } else { " ✎" };
+    format!("{}{}", category, note)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use cidiff_annotations::DatasetSpec;
+    use ratatui::crossterm::event::KeyEvent;
+
+    use super::*;
+
+    /// A log opened in a dataset of the temporary directory.
+    fn opened(name: &str, log: &str) -> FileOpened {
+        let path = std::env::temp_dir().join(format!("cidiff-{}-{}", name, std::process::id()));
+        let pair = path.join("owner/repo/run1-0");
+        fs::create_dir_all(&pair).unwrap();
+        fs::write(pair.join("success.log"), log).unwrap();
+        fs::write(pair.join("failure.log"), log).unwrap();
+        let dataset = Dataset::new(&path, DatasetSpec::default());
+        let opened = FileOpened::new(
+            &LogParser::new(),
+            &dataset,
+            "owner/repo/run1-0".to_string(),
+            Annotations::new(),
+        )
+        .unwrap();
+        fs::remove_dir_all(&path).unwrap();
+        opened
+    }
+
+    fn press(opened: &mut FileOpened, keys: &str) {
+        let mut clipboard = Clipboard::new();
+        for c in keys.chars() {
+            let event = Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            opened
+                .handle_input(Rect::new(0, 0, 80, 24), &event, &mut clipboard)
+                .unwrap();
+        }
+    }
+
+    fn selected(opened: &FileOpened) -> Vec<usize> {
+        let mut lines = opened.selections().iter().map(|s| s.line).collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn the_visual_mode_changes_the_displayed_lines_of_the_range() {
+        let mut opened = opened(
+            "visual",
+            "##[group]Run make\n##[group]Output\na\nb\n##[endgroup]\nerror\n",
+        );
+        // the lines hidden in the folded group are left as they are
+        press(&mut opened, "jzgvGs");
+        assert_eq!(selected(&opened), vec![0, 1, 5]);
+        assert_eq!(opened.anchor, None);
+
+        press(&mut opened, "Zgjjvjjj ");
+        assert_eq!(selected(&opened), vec![0, 1, 2, 3, 4]);
+        press(&mut opened, "gvjd");
+        assert_eq!(selected(&opened), vec![2, 3, 4]);
+        // leaving the visual mode changes nothing
+        press(&mut opened, "vjv");
+        assert_eq!(selected(&opened), vec![2, 3, 4]);
+    }
 }
@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, SubsecRound};
+use ratatui::crossterm::event::Event;
+
+use crate::Session;
+
+/// After this long without any event, the annotator is idle: the time is not counted anymore.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The time spent on an opened log. The time between two events of the terminal is counted while
+/// the terminal has the focus, up to [`IDLE_TIMEOUT`].
+pub struct Timer {
+    opened: DateTime<Local>,
+    active: Duration,
+    last_event: Instant,
+    focused: bool,
+}
+
+impl Timer {
+    pub fn start() -> Self {
+        Self {
+            opened: Local::now().trunc_subsecs(0),
+            active: Duration::ZERO,
+            last_event: Instant::now(),
+            focused: true,
+        }
+    }
+
+    pub fn event(&mut self, event: &Event) {
+        self.event_at(event, Instant::now());
+    }
+
+    fn event_at(&mut self, event: &Event, now: Instant) {
+        if self.focused {
+            self.active += (now - self.last_event).min(IDLE_TIMEOUT);
+        }
+        self.last_event = now;
+        // not every terminal sends the focus events: a key or a click means that it has the focus
+        self.focused = !matches!(event, Event::FocusLost);
+    }
+
+    /// The session, when the log is closed.
+    pub fn stop(&self) -> Session {
+        Session {
+            opened: self.opened,
+            closed: Local::now().trunc_subsecs(0),
+            active: self.active.as_secs(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+    use super::*;
+    use crate::LogAnnotation;
+
+    #[test]
+    fn only_the_focused_time_before_the_idle_timeout_is_counted() {
+        let key = Event::Key(KeyEvent::from(KeyCode::Char('j')));
+        let mut timer = Timer::start();
+        let start = timer.last_event;
+        timer.event_at(&key, start + Duration::from_secs(5));
+        assert_eq!(timer.active, Duration::from_secs(5));
+        // idle for 10 minutes
+        timer.event_at(&key, start + Duration::from_secs(605));
+        assert_eq!(timer.active, Duration::from_secs(5) + IDLE_TIMEOUT);
+        // in another window for 30 seconds
+        timer.event_at(&Event::FocusLost, start + Duration::from_secs(610));
+        timer.event_at(&Event::FocusGained, start + Duration::from_secs(640));
+        timer.event_at(&key, start + Duration::from_secs(641));
+        assert_eq!(timer.active, Duration::from_secs(71));
+
+        let session = timer.stop();
+        assert_eq!(session.active, 71);
+        assert!(session.opened <= session.closed);
+    }
+
+    #[test]
+    fn a_log_only_opened_is_kept_for_its_sessions() {
+        let mut timer = Timer::start();
+        timer.event(&Event::FocusGained);
+        let annotation = LogAnnotation {
+            sessions: vec![timer.stop()],
+            ..Default::default()
+        };
+        assert!(!annotation.is_annotated());
+        assert!(!annotation.is_empty());
+    }
+}
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use cidiff_annotations::LogLine;
 use ratatui::{
     layout::Rect,
     style::{Color, Style, Stylize},
@@ -5,18 +8,42 @@ use ratatui::{
     widgets::Widget,
 };
 
+use regex::Regex;
+
+use crate::{apollo::adjudication::Decision, search, Selection};
+
 pub struct PathListWidget<'a> {
     paths: &'a Vec<&'a str>,
     start: usize,
     highlighted: usize,
     annotated: Vec<&'a str>,
+    /// What is shown after the path of the logs, e.g. their category
+    tags: HashMap<&'a str, String>,
 }
 pub struct LogFileWdiget<'a> {
-    lines: &'a Vec<String>,
+    lines: &'a [LogLine],
+    /// The line displayed in each row
+    rows: &'a [usize],
+    /// The first displayed row
     start: usize,
     line_start: usize,
+    /// The highlighted row
     highlighted: usize,
-    annotated: Option<&'a Vec<usize>>,
+    annotated: Option<&'a Vec<Selection>>,
+    /// The first and last rows of the range of the visual mode
+    visual: Option<(usize, usize)>,
+    search: Option<&'a Regex>,
+}
+
+/// The votes of the annotators for the lines displayed by a [`LogFileWdiget`] next to it (how many
+/// and who selected each line), with the decision of the adjudicator.
+pub struct VotesWidget<'a> {
+    rows: &'a [usize],
+    start: usize,
+    highlighted: usize,
+    annotators: usize,
+    votes: &'a HashMap<usize, Vec<String>>,
+    decisions: &'a [Decision],
 }
 
 impl<'a> PathListWidget<'a> {
@@ -26,6 +53,7 @@ impl<'a> PathListWidget<'a> {
             start: 0,
             highlighted: 0,
             annotated: vec![],
+            tags: HashMap::new(),
         }
     }
 
@@ -43,6 +71,11 @@ impl<'a> PathListWidget<'a> {
         self.annotated = annotated;
         self
     }
+
+    pub fn tags(mut self, tags: HashMap<&'a str, String>) -> Self {
+        self.tags = tags;
+        self
+    }
 }
 
 impl Widget for PathListWidget<'_> {
@@ -70,6 +103,10 @@ impl Widget for PathListWidget<'_> {
                 Span::styled(if self.highlighted == index { ">" } else { " " }, style),
                 Span::styled(if annotated { "✓" } else { " " }, style),
                 Span::styled(self.paths[index], style),
+                Span::styled(
+                    self.tags.get(self.paths[index]).map_or("", |t| &t[..]),
+                    style.fg(Color::Magenta),
+                ),
             ]);
             buf.set_line(area.x, area.y + i, &line, area.width);
         }
@@ -77,13 +114,16 @@ impl Widget for PathListWidget<'_> {
 }
 
 impl<'a> LogFileWdiget<'a> {
-    pub fn new(lines: &'a Vec<String>, annotated: Option<&'a Vec<usize>>) -> Self {
+    pub fn new(lines: &'a [LogLine], rows: &'a [usize], annotated: Option<&'a Vec<Selection>>) -> Self {
         Self {
             lines,
+            rows,
             start: 0,
             line_start: 0,
             highlighted: 0,
             annotated,
+            visual: None,
+            search: None,
         }
     }
 
@@ -101,6 +141,16 @@ impl<'a> LogFileWdiget<'a> {
         self.line_start = line_start;
         self
     }
+
+    pub fn visual(mut self, visual: Option<(usize, usize)>) -> Self {
+        self.visual = visual;
+        self
+    }
+
+    pub fn search(mut self, search: Option<&'a Regex>) -> Self {
+        self.search = search;
+        self
+    }
 }
 
 impl Widget for LogFileWdiget<'_> {
@@ -109,44 +159,157 @@ impl Widget for LogFileWdiget<'_> {
         Self: Sized,
     {
         for i in 0..area.height {
-            let index = i as usize + self.start;
-            if index >= self.lines.len() {
+            let row = i as usize + self.start;
+            if row >= self.rows.len() {
                 break;
             }
-            let annotated = self.annotated.map(|v| v.contains(&index)).unwrap_or(false);
+            let index = self.rows[row];
+            let selection = self.annotated.and_then(|v| v.iter().find(|s| s.line == index));
+            let annotated = selection.is_some();
+            // the number of lines hidden after this one by a folded section
+            let folded = self.rows.get(row + 1).copied().unwrap_or(self.lines.len()) - index - 1;
 
-            let style = if self.highlighted == index {
+            let style = if self.highlighted == row {
                 Style::new().bold().underlined()
             } else {
                 Style::new()
             };
-            let style = if self.lines[index].to_lowercase().contains("warn") {
+            let style = if self.lines[index].content.to_lowercase().contains("warn") {
                 style.fg(Color::Yellow)
-            } else if self.lines[index].to_lowercase().contains("error") {
+            } else if self.lines[index].content.to_lowercase().contains("error") {
                 style.fg(Color::Red)
             } else {
                 style
             };
             let style = if annotated { style.bg(Color::Green) } else { style };
+            let style = match self.visual {
+                Some((first, last)) if (first..=last).contains(&row) => style.reversed(),
+                _ => style,
+            };
 
-            let text: String = self.lines[index].chars().skip(self.line_start).collect();
-            let line = Line::from(vec![
+            let mut spans = vec![
                 Span::styled(
                     format!("{:1$}", index, self.lines.len().to_string().chars().count()),
                     style.fg(Color::DarkGray),
                 ),
-                if self.highlighted == index {
+                if self.highlighted == row {
                     Span::styled(" > ", style)
+                } else if folded > 0 {
+                    Span::styled(" ▸ ", style)
                 } else {
                     Span::styled("   ", style)
                 },
-                if annotated {
-                    Span::styled(" ✓ ", style.fg(Color::Blue))
-                } else {
-                    Span::styled("   ", style)
+                match selection {
+                    // the first letter of the label and the severity
+                    Some(Selection {
+                        label: Some(label),
+                        severity,
+                        ..
+                    }) => Span::styled(
+                        format!(
+                            " {}{}",
+                            label.chars().next().unwrap_or('✓').to_uppercase(),
+                            severity.map(|s| s.to_string()).unwrap_or(" ".to_string())
+                        ),
+                        style.fg(Color::Blue),
+                    ),
+                    Some(Selection {
+                        severity: Some(severity),
+                        ..
+                    }) => Span::styled(format!(" ✓{}", severity), style.fg(Color::Blue)),
+                    Some(_) => Span::styled(" ✓ ", style.fg(Color::Blue)),
+                    None => Span::styled("   ", style),
                 },
-                Span::styled(&text, style),
-            ]);
+            ];
+            for (part, matched) in
+                search::split_matches(self.search, &self.lines[index].content, self.line_start)
+            {
+                spans.push(if matched {
+                    Span::styled(part, style.bg(Color::Yellow).fg(Color::Black))
+                } else {
+                    Span::styled(part, style)
+                });
+            }
+            if folded > 0 {
+                spans.push(Span::styled(
+                    format!(" [{} folded lines]", folded),
+                    style.fg(Color::DarkGray),
+                ));
+            }
+            buf.set_line(area.x, area.y + i, &Line::from(spans), area.width);
+        }
+    }
+}
+
+impl<'a> VotesWidget<'a> {
+    pub fn new(
+        rows: &'a [usize],
+        annotators: usize,
+        votes: &'a HashMap<usize, Vec<String>>,
+        decisions: &'a [Decision],
+    ) -> Self {
+        Self {
+            rows,
+            start: 0,
+            highlighted: 0,
+            annotators,
+            votes,
+            decisions,
+        }
+    }
+
+    pub fn highlighted(mut self, highlighted: usize) -> Self {
+        self.highlighted = highlighted;
+        self
+    }
+
+    pub fn start(mut self, start: usize) -> Self {
+        self.start = start;
+        self
+    }
+
+    fn label(&self, voters: &[String]) -> String {
+        format!("{}/{} {}", voters.len(), self.annotators, voters.join(","))
+    }
+
+    /// The width needed to display the votes of every line, with the mark of the decision.
+    pub fn width(&self) -> u16 {
+        self.votes
+            .values()
+            .map(|voters| self.label(voters).chars().count() as u16 + 2)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Widget for VotesWidget<'_> {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        for i in 0..area.height {
+            let row = i as usize + self.start;
+            if row >= self.rows.len() {
+                break;
+            }
+            let index = self.rows[row];
+            let voters = self.votes.get(&index).map(|v| &v[..]).unwrap_or_default();
+            let votes = voters.len();
+            if votes == 0 {
+                continue;
+            }
+            let style = if self.highlighted == row {
+                Style::new().bold()
+            } else {
+                Style::new()
+            };
+            let (mark, style) = match self.decisions.iter().find(|d| d.line == index) {
+                Some(decision) if decision.accepted => ("✓", style.fg(Color::Green)),
+                Some(_) => ("✗", style.fg(Color::Red)),
+                None if votes < self.annotators => ("?", style.fg(Color::Yellow)),
+                None => (" ", style.fg(Color::DarkGray)),
+            };
+            let line = Line::from(Span::styled(format!("{} {}", mark, self.label(voters)), style));
             buf.set_line(area.x, area.y + i, &line, area.width);
         }
     }
@@ -1,11 +1,14 @@
-use std::{collections::HashMap, io, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf};
 
 use crate::{
     arthemis::state::{FileChooser, FileOpened},
+    clipboard::Clipboard,
+    error::draw_error,
     WhatToDo,
 };
+use anyhow::Context;
+use cidiff_annotations::{evaluation::SelectionColumns, Dataset, LogParser, Result};
 use clap::Parser;
-use copypasta::ClipboardContext;
 use ratatui::{
     crossterm::{
         event::{
@@ -28,98 +31,164 @@ pub struct ArthemisArgs {
     /// The path to the anotation produced by the algorithms seed, lcs, gpt, keyword,
     /// bigram-raw, and bigram-drain
     merged_path: String,
+    /// Only show the lines selected by the humans with this label
+    #[arg(long)]
+    label: Option<String>,
+    /// Show the success log next to the failure log when a log is opened (toggled with `D`)
+    #[arg(long)]
+    side_by_side: bool,
+    /// Show the near misses of the algorithms: the lines they selected at most this many lines
+    /// away from a line selected by the humans (one line for each human line)
+    #[arg(short, long, default_value_t = 0)]
+    tolerance: usize,
 }
 
-pub fn bootstrap(args: ArthemisArgs, dataset_path: &str, log_paths: Vec<PathBuf>) -> io::Result<()> {
-    let annotations = load_merged_selections(args.merged_path);
+pub fn bootstrap(
+    args: ArthemisArgs,
+    dataset: &Dataset,
+    log_paths: Vec<PathBuf>,
+    parser: &LogParser,
+) -> Result<()> {
+    let annotations = load_merged_selections(args.merged_path, args.label.as_deref())?;
     println!("selection computed");
-    let mut terminal = ratatui::init();
-    execute!(std::io::stdout(), EnableFocusChange, EnableMouseCapture)?;
-    terminal.clear()?;
-    run(terminal, dataset_path, log_paths, annotations)?;
-    execute!(std::io::stdout(), DisableFocusChange, DisableMouseCapture)?;
+    let terminal = ratatui::init();
+    let app_result = execute!(std::io::stdout(), EnableFocusChange, EnableMouseCapture)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| {
+            run(
+                terminal,
+                dataset,
+                log_paths,
+                annotations,
+                parser,
+                args.side_by_side,
+                args.tolerance,
+            )
+        });
+    let _ = execute!(std::io::stdout(), DisableFocusChange, DisableMouseCapture);
     ratatui::restore();
-    Ok(())
+    app_result
 }
 
-type Record = (String, String, usize);
-
 /// Load the selection of the algorithms as a map:
 /// log path -> map of (line_number -> selections_by_algorithm)
 /// selections_by_algorithm: `[human, cidiff, lcs, gpt, keyword]`
-fn load_merged_selections(merged_path: String) -> HashMap<String, HashMap<usize, Vec<bool>>> {
+/// With a label, only the human selections with this label are kept.
+fn load_merged_selections(
+    merged_path: String,
+    label: Option<&str>,
+) -> Result<HashMap<String, HashMap<usize, Vec<bool>>>> {
     let mut map = HashMap::new();
-    let reader = csv::ReaderBuilder::new().from_path(&merged_path);
-    if let Ok(mut reader) = reader {
-        // to compute the size of the csv, iter over the record, then go back to the start of the csv
-        let start = reader.position().clone();
-        let size = reader.records().count();
-        let _ = reader.seek(start);
-        for (csv_index, record) in reader.deserialize::<Record>().enumerate() {
-            if let Ok(record) = record {
-                let path = record.0;
-                let selected_line = record.2;
-                print!("\rreading line {}/{}", csv_index, size);
-                let i = match &record.1[..] {
-                    "human" => 0,
-                    "seed" => 1,
-                    "lcs" => 2,
-                    "gpt" => 3,
-                    "keyword" => 4,
-                    "bigram-raw" => 5,
-                    "bigram-drain" => 6,
-                    _ => 10,
-                };
-                map.entry(path.to_string())
-                    .and_modify(|a: &mut HashMap<usize, Vec<bool>>| {
-                        a.entry(selected_line)
-                            .and_modify(|selections| selections[i] = true)
-                            .or_insert(vec![i == 0, i == 1, i == 2, i == 3, i == 4, i == 5, i == 6]);
-                    })
-                    .or_insert(HashMap::from([(
-                        selected_line,
-                        vec![i == 0, i == 1, i == 2, i == 3, i == 4, i == 5, i == 6],
-                    )]));
-            } else {
-                println!("error reading line {}: {}\n", csv_index, record.unwrap_err());
-            }
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(&merged_path)
+        .with_context(|| format!("could not read {}", merged_path))?;
+    let columns = reader
+        .headers()
+        .map_err(anyhow::Error::from)
+        .and_then(SelectionColumns::new)
+        .with_context(|| format!("could not read the header of {}", merged_path))?;
+    // to compute the size of the csv, iter over the record, then go back to the start of the csv
+    let start = reader.position().clone();
+    let size = reader.records().count();
+    let _ = reader.seek(start);
+    for (csv_index, record) in reader.records().enumerate() {
+        let record = record
+            .map_err(anyhow::Error::from)
+            .and_then(|r| columns.parse(&r));
+        if let Ok((path, kind, selected_line, selection_label)) = record {
+            print!("\rreading line {}/{}", csv_index, size);
+            let i = match &kind[..] {
+                // the annotators are merged
+                t if t == "human" || t.starts_with("human:") => {
+                    if label.is_some() && selection_label.as_deref() != label {
+                        continue;
+                    }
+                    0
+                }
+                "seed" => 1,
+                "lcs" => 2,
+                "gpt" => 3,
+                "keyword" => 4,
+                "bigram-raw" => 5,
+                "bigram-drain" => 6,
+                // e.g. the gold set
+                _ => continue,
+            };
+            map.entry(path.to_string())
+                .and_modify(|a: &mut HashMap<usize, Vec<bool>>| {
+                    a.entry(selected_line)
+                        .and_modify(|selections| selections[i] = true)
+                        .or_insert(vec![i == 0, i == 1, i == 2, i == 3, i == 4, i == 5, i == 6]);
+                })
+                .or_insert(HashMap::from([(
+                    selected_line,
+                    vec![i == 0, i == 1, i == 2, i == 3, i == 4, i == 5, i == 6],
+                )]));
+        } else {
+            println!("error reading line {}: {}\n", csv_index, record.unwrap_err());
         }
-        println!();
     }
-    map
+    println!();
+    Ok(map)
 }
 
 fn run(
     mut terminal: DefaultTerminal,
-    dataset_path: &str,
+    dataset: &Dataset,
     log_paths: Vec<PathBuf>,
     annotations: HashMap<String, HashMap<usize, Vec<bool>>>,
-) -> io::Result<()> {
+    parser: &LogParser,
+    side_by_side: bool,
+    tolerance: usize,
+) -> Result<()> {
+    terminal.clear()?;
     let mut log_paths = log_paths
         .iter()
         .map(|p| p.to_str().unwrap())
         .collect::<Vec<&str>>();
     log_paths.sort();
-    let mut clipboard = ClipboardContext::new().unwrap();
+    let mut clipboard = Clipboard::new();
     let mut state: Box<dyn State> = Box::new(FileChooser::new(&log_paths));
     let mut last_position = (0, 0);
+    let mut error: Option<String> = None;
     loop {
         let completer_frames = terminal.draw(|frame| {
             state.draw(frame);
+            if let Some(error) = &error {
+                draw_error(frame, error);
+            }
         })?;
         let area = completer_frames.area;
         let e = event::read()?;
-        let what_to_do = state.handle_input(area, &e, &mut clipboard);
+        if let Event::Key(_) = e {
+            error = None;
+        }
+        let what_to_do = state.handle_input(area, &e, &mut clipboard).unwrap_or_else(|e| {
+            error = Some(format!("{:#}", e));
+            WhatToDo::StayOnSameState
+        });
         match what_to_do {
             WhatToDo::Exit => return Ok(()),
             WhatToDo::StayOnSameState => {}
             WhatToDo::OpenFile((start, path_index)) => {
-                last_position = (start, path_index);
-                state = Box::new(FileOpened::new(
-                    dataset_path,
+                let opened = FileOpened::new(
+                    parser,
+                    dataset,
                     log_paths[path_index].to_string(),
-                    annotations.get(log_paths[path_index]).unwrap().clone(),
-                ));
+                    annotations
+                        .get(log_paths[path_index])
+                        .cloned()
+                        .unwrap_or_default(),
+                )
+                .map(|opened| opened.side_by_side(side_by_side).tolerance(tolerance));
+                match opened {
+                    Ok(opened) => {
+                        last_position = (start, path_index);
+                        state = Box::new(opened);
+                    }
+                    Err(e) => error = Some(format!("{:#}", e)),
+                }
             }
             WhatToDo::ListDir => {
                 state = Box::new(
@@ -133,6 +202,6 @@ fn run(
 }
 
 pub trait State {
-    fn handle_input(&mut self, area: Rect, event: &Event, clipboard: &mut ClipboardContext) -> WhatToDo;
+    fn handle_input(&mut self, area: Rect, event: &Event, clipboard: &mut Clipboard) -> Result<WhatToDo>;
     fn draw(&self, frame: &mut Frame);
 }
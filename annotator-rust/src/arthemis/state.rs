@@ -1,13 +1,17 @@
-use std::{collections::HashMap, fs, path::Path};
+use std::collections::{HashMap, HashSet};
 
 use crate::{
     arthemis::{
         widget::{LogFileWdiget, PathListWidget},
         State,
     },
-    parse_file, WhatToDo,
+    clipboard::Clipboard,
+    search::Search,
+    side_by_side::SideBySide,
+    WhatToDo,
 };
-use copypasta::{ClipboardContext, ClipboardProvider};
+use anyhow::Context;
+use cidiff_annotations::{evaluation, steps::Folds, Dataset, Log, LogParser, Result};
 use ratatui::{
     crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers, MouseEventKind},
     layout::{Alignment, Constraint, Layout, Margin, Rect},
@@ -18,18 +22,30 @@ use ratatui::{
     Frame,
 };
 
+/// The humans and the algorithms in the selections of each line.
+const HUMAN_AND_ALGORITHMS: usize = 7;
+
 pub struct FileChooser<'a> {
     start: usize,
     highlighted: usize,
     log_paths: &'a Vec<&'a str>,
 }
 pub struct FileOpened {
+    /// The first displayed row
     start: usize,
     line_start: usize,
+    /// The highlighted row
     highlighted: usize,
     log_path: String,
-    lines: Vec<String>,
+    log: Log,
+    /// The sections folded by the user, and the lines displayed in each row
+    folds: Folds,
     selections: HashMap<usize, Vec<bool>>,
+    /// The lines of each algorithm that are close to a line of the humans, with a tolerance
+    near_misses: HashMap<usize, Vec<bool>>,
+    tolerance: usize,
+    search: Search,
+    side_by_side: SideBySide,
 }
 impl<'a> FileChooser<'a> {
     pub fn new(log_paths: &'a Vec<&'a str>) -> Self {
@@ -52,42 +68,36 @@ impl<'a> FileChooser<'a> {
 }
 
 impl State for FileChooser<'_> {
-    fn handle_input(&mut self, area: Rect, e: &Event, clipboard: &mut ClipboardContext) -> WhatToDo {
+    fn handle_input(&mut self, area: Rect, e: &Event, clipboard: &mut Clipboard) -> Result<WhatToDo> {
         match e {
             Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-                KeyCode::Char('q') => return WhatToDo::Exit,
+                KeyCode::Char('q') => return Ok(WhatToDo::Exit),
                 KeyCode::Char('j') => self.highlighted += 1,
                 KeyCode::Char('k') => self.highlighted = self.highlighted.saturating_sub(1),
-                KeyCode::Char('d') => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        self.highlighted += (area.height / 2) as usize;
-                    }
+                KeyCode::Char('d') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted += (area.height / 2) as usize;
                 }
-                KeyCode::Char('u') => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
-                    }
+                KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
                 }
                 KeyCode::Char('g') => self.highlighted = 0,
                 KeyCode::Char('G') => self.highlighted = self.log_paths.len() - 1,
                 KeyCode::Char('y') => {
-                    let _ = clipboard.set_contents(self.log_paths[self.highlighted].to_string());
+                    clipboard.copy(self.log_paths[self.highlighted].to_string())?;
                 }
-                KeyCode::Enter => return WhatToDo::OpenFile((self.start, self.highlighted)),
+                KeyCode::Enter => return Ok(WhatToDo::OpenFile((self.start, self.highlighted))),
                 _ => (),
             },
             Event::Mouse(mouse) => match mouse.kind {
-                MouseEventKind::ScrollUp => {
-                    if self.start >= 2 {
-                        self.start = self.start.saturating_sub(2);
-                        self.highlighted = self.highlighted.saturating_sub(2);
-                    }
+                MouseEventKind::ScrollUp if self.start >= 2 => {
+                    self.start = self.start.saturating_sub(2);
+                    self.highlighted = self.highlighted.saturating_sub(2);
                 }
-                MouseEventKind::ScrollDown => {
-                    if self.start + 2 + area.height as usize - 4 < self.log_paths.len() {
-                        self.start += 2;
-                        self.highlighted += 2;
-                    }
+                MouseEventKind::ScrollDown
+                    if self.start + 2 + area.height as usize - 4 < self.log_paths.len() =>
+                {
+                    self.start += 2;
+                    self.highlighted += 2;
                 }
                 _ => {}
             },
@@ -102,7 +112,7 @@ impl State for FileChooser<'_> {
         if self.start + area.height as usize - 4 < self.highlighted {
             self.start = self.highlighted - (area.height as usize - 4);
         }
-        WhatToDo::StayOnSameState
+        Ok(WhatToDo::StayOnSameState)
     }
 
     fn draw(&self, frame: &mut Frame) {
@@ -152,27 +162,76 @@ impl State for FileChooser<'_> {
 }
 
 impl FileOpened {
-    pub fn new(dataset_path: &str, log_path: String, selections: HashMap<usize, Vec<bool>>) -> Self {
-        let lines = fs::read_to_string(Path::new(dataset_path).join(&log_path).join("failure.log"))
-            .map(parse_file)
-            .unwrap_or_default();
-        Self {
+    pub fn new(
+        parser: &LogParser,
+        dataset: &Dataset,
+        log_path: String,
+        selections: HashMap<usize, Vec<bool>>,
+    ) -> Result<Self> {
+        let failure_log = dataset.failure_log(&log_path);
+        let log = parser
+            .parse_log_file(&failure_log)
+            .with_context(|| format!("could not read {}", failure_log.display()))?;
+        let folds = Folds::new(&log);
+        Ok(Self {
             start: 0,
             line_start: 0,
             highlighted: 0,
+            side_by_side: SideBySide::new(parser, dataset, &log_path),
             log_path,
-            lines,
+            log,
+            folds,
             selections,
+            near_misses: HashMap::new(),
+            tolerance: 0,
+            search: Search::default(),
+        })
+    }
+
+    /// Show the success log next to the failure log.
+    pub fn side_by_side(mut self, shown: bool) -> Self {
+        if shown != self.side_by_side.is_shown() {
+            self.side_by_side.toggle(&self.log.lines);
         }
+        self
+    }
+
+    /// Show the lines selected by the algorithms at most `tolerance` lines away from a line selected
+    /// by the humans.
+    pub fn tolerance(mut self, tolerance: usize) -> Self {
+        self.tolerance = tolerance;
+        self.near_misses.clear();
+        if tolerance == 0 {
+            return self;
+        }
+        let selected_by = |i: usize| {
+            self.selections
+                .iter()
+                .filter(|(_, s)| s[i])
+                .map(|(line, _)| *line)
+                .collect::<HashSet<_>>()
+        };
+        let human = selected_by(0);
+        for i in 1..HUMAN_AND_ALGORITHMS {
+            for line in evaluation::near_misses(&human, &selected_by(i), tolerance) {
+                self.near_misses
+                    .entry(line)
+                    .or_insert(vec![false; HUMAN_AND_ALGORITHMS])[i] = true;
+            }
+        }
+        self
     }
 }
 
 impl State for FileOpened {
-    fn handle_input(&mut self, area: Rect, e: &Event, _: &mut ClipboardContext) -> WhatToDo {
+    fn handle_input(&mut self, area: Rect, e: &Event, _: &mut Clipboard) -> Result<WhatToDo> {
         match e {
+            Event::Key(key) if key.kind == KeyEventKind::Press && self.search.is_typing() => {
+                self.highlighted = self.search.input(key.code, &self.log.lines, self.folds.rows())?;
+            }
             Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
                 KeyCode::Char('q') => {
-                    return WhatToDo::ListDir;
+                    return Ok(WhatToDo::ListDir);
                 }
                 KeyCode::Char('j') => self.highlighted += 1,
                 KeyCode::Char('k') => self.highlighted = self.highlighted.saturating_sub(1),
@@ -180,41 +239,48 @@ impl State for FileOpened {
                 KeyCode::Char('L') => self.line_start += 10,
                 KeyCode::Char('h') => self.line_start = self.line_start.saturating_sub(1),
                 KeyCode::Char('H') => self.line_start = self.line_start.saturating_sub(10),
-                KeyCode::Char('d') => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        self.highlighted += (area.height / 2) as usize;
-                        self.start += (area.height / 2) as usize;
-                    }
+                KeyCode::Char('d') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted += (area.height / 2) as usize;
+                    self.start += (area.height / 2) as usize;
                 }
-                KeyCode::Char('u') => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
-                        self.start = self.start.saturating_sub((area.height as usize) / 2);
-                    }
+                KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+                    self.highlighted = self.highlighted.saturating_sub((area.height as usize) / 2);
+                    self.start = self.start.saturating_sub((area.height as usize) / 2);
                 }
                 KeyCode::Char('g') => self.highlighted = 0,
-                KeyCode::Char('G') => self.highlighted = self.lines.len() - 1,
+                KeyCode::Char('G') => self.highlighted = self.folds.rows().len().saturating_sub(1),
+                KeyCode::Char('z') => self.highlighted = self.folds.toggle(&self.log, self.highlighted),
+                KeyCode::Char('Z') => self.highlighted = self.folds.toggle_all(&self.log, self.highlighted),
+                KeyCode::Char('D') => self.side_by_side.toggle(&self.log.lines),
+                KeyCode::Char('/') => self.search.start(true, self.highlighted),
+                KeyCode::Char('?') => self.search.start(false, self.highlighted),
+                KeyCode::Char(c @ ('n' | 'N')) => {
+                    if let Some(row) =
+                        self.search
+                            .next(&self.log.lines, self.folds.rows(), self.highlighted, c == 'N')
+                    {
+                        self.highlighted = row;
+                    }
+                }
                 _ => (),
             },
             Event::Mouse(mouse) => match mouse.kind {
-                MouseEventKind::ScrollUp => {
-                    if self.start >= 2 {
-                        self.start = self.start.saturating_sub(2);
-                        self.highlighted = self.highlighted.saturating_sub(2);
-                    }
+                MouseEventKind::ScrollUp if self.start >= 2 => {
+                    self.start = self.start.saturating_sub(2);
+                    self.highlighted = self.highlighted.saturating_sub(2);
                 }
-                MouseEventKind::ScrollDown => {
-                    if self.start + 2 + area.height as usize - 4 < self.lines.len() {
-                        self.start += 2;
-                        self.highlighted += 2;
-                    }
+                MouseEventKind::ScrollDown
+                    if self.start + 2 + area.height as usize - 4 < self.folds.rows().len() =>
+                {
+                    self.start += 2;
+                    self.highlighted += 2;
                 }
                 _ => {}
             },
             _ => {}
         }
-        if self.highlighted >= self.lines.len() {
-            self.highlighted = self.lines.len() - 1;
+        if self.highlighted >= self.folds.rows().len() {
+            self.highlighted = self.folds.rows().len().saturating_sub(1);
         }
         if self.start > self.highlighted {
             self.start = self.highlighted;
@@ -222,28 +288,50 @@ impl State for FileOpened {
         if self.start + area.height as usize - 4 < self.highlighted {
             self.start = self.highlighted - (area.height as usize - 4);
         }
-        WhatToDo::StayOnSameState
+        Ok(WhatToDo::StayOnSameState)
     }
 
     fn draw(&self, frame: &mut Frame) {
         let area = frame.area();
 
-        let widget_area = Rect::new(area.x, area.y, area.width, area.height - 3);
+        let (success_area, widget_area) =
+            self.side_by_side
+                .split(Rect::new(area.x, area.y, area.width, area.height - 3));
+        if let Some(success_area) = success_area {
+            let line = self
+                .folds
+                .rows()
+                .get(self.highlighted)
+                .copied()
+                .unwrap_or_default();
+            let widget = self
+                .side_by_side
+                .widget(line, self.highlighted - self.start, self.line_start);
+            frame.render_widget(widget, success_area);
+        }
         let widget = LogFileWdiget::new(
-            &self.lines,
+            &self.log.lines,
+            self.folds.rows(),
             self.selections.clone(),
             //self.drain.keys().map(|k| *k).collect(),
         )
         .start(self.start)
         .line_start(self.line_start)
-        .highlighted(self.highlighted);
+        .highlighted(self.highlighted)
+        .near_misses(&self.near_misses)
+        .search(self.search.regex());
 
         frame.render_widget(widget, widget_area);
 
         let scrollbar = Scrollbar::new(ratatui::widgets::ScrollbarOrientation::VerticalRight)
             .symbols(scrollbar::VERTICAL);
-        let mut scrollbar_state =
-            ScrollbarState::new(self.lines.len() - widget_area.height as usize).position(self.start);
+        let mut scrollbar_state = ScrollbarState::new(
+            self.folds
+                .rows()
+                .len()
+                .saturating_sub(widget_area.height as usize),
+        )
+        .position(self.start);
         frame.render_stateful_widget(
             scrollbar,
             widget_area.inner(Margin {
@@ -269,8 +357,17 @@ impl State for FileOpened {
         frame.render_widget(file_paragraph, layout[0]);
 
         let symbols_block = Block::bordered().title("Symbols").border_set(border::THICK);
-        let symbols_text = Span::raw("☘ Cidiff | ⚐ Lcs | ⚙ Gpt | ⚷ Keyword | ☍ Bigram | ⛆ Bigram-drain");
-        let symbols_paragraph = Paragraph::new(symbols_text).block(symbols_block);
+        let mut symbols_text = vec![Span::raw(
+            "☘ Cidiff | ⚐ Lcs | ⚙ Gpt | ⚷ Keyword | ☍ Bigram | ⛆ Bigram-drain",
+        )];
+        if self.tolerance > 0 {
+            symbols_text.push(Span::raw(" | "));
+            symbols_text.push(Span::styled(
+                format!("near miss (±{})", self.tolerance),
+                Style::default().fg(Color::Magenta),
+            ));
+        }
+        let symbols_paragraph = Paragraph::new(Line::from(symbols_text)).block(symbols_block);
 
         frame.render_widget(symbols_paragraph, layout[1]);
 
@@ -280,6 +377,16 @@ impl State for FileOpened {
             Span::styled("<j> ", Style::default().fg(Color::Blue)),
             Span::styled("<k> ", Style::default().fg(Color::Blue)),
             Span::styled("<l>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Fold step "),
+            Span::styled("<z> ", Style::default().fg(Color::Blue)),
+            Span::styled("<Z>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Success log "),
+            Span::styled("<D>", Style::default().fg(Color::Blue)),
+            Span::raw(" | Search "),
+            Span::styled("</> ", Style::default().fg(Color::Blue)),
+            Span::styled("<?> ", Style::default().fg(Color::Blue)),
+            Span::styled("<n> ", Style::default().fg(Color::Blue)),
+            Span::styled("<N>", Style::default().fg(Color::Blue)),
             Span::raw(" | Top "),
             Span::styled("<g>", Style::default().fg(Color::Blue)),
             Span::raw(" | Bottom "),
@@ -287,6 +394,11 @@ impl State for FileOpened {
             Span::raw(" | Return "),
             Span::styled("<q>", Style::default().fg(Color::Blue)),
         ]);
+        if let Some(prompt) = self.search.prompt(&self.log.lines, self.folds.rows()) {
+            let search_block = Block::bordered().title("Search").border_set(border::THICK);
+            frame.render_widget(Paragraph::new(prompt).block(search_block), layout[2]);
+            return;
+        }
         let instruction_block = Block::bordered().title("Instructions").border_set(border::THICK);
         let instruction_paragraph = Paragraph::new(instructions)
             .block(instruction_block)
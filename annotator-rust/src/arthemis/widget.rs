@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 
+use cidiff_annotations::LogLine;
 use ratatui::{
     layout::Rect,
     style::{Color, Style, Stylize},
     text::{Line, Span},
     widgets::Widget,
 };
+use regex::Regex;
+
+use crate::search;
 
 pub struct PathListWidget<'a> {
     paths: &'a Vec<&'a str>,
@@ -58,21 +62,31 @@ impl Widget for PathListWidget<'_> {
     }
 }
 pub struct LogFileWdiget<'a> {
-    lines: &'a Vec<String>,
+    lines: &'a [LogLine],
+    /// The line displayed in each row
+    rows: &'a [usize],
+    /// The first displayed row
     start: usize,
     line_start: usize,
+    /// The highlighted row
     highlighted: usize,
     selected: HashMap<usize, Vec<bool>>,
+    /// The algorithms whose selection of a line is a near miss
+    near_misses: Option<&'a HashMap<usize, Vec<bool>>>,
+    search: Option<&'a Regex>,
 }
 
 impl<'a> LogFileWdiget<'a> {
-    pub fn new(lines: &'a Vec<String>, selected: HashMap<usize, Vec<bool>>) -> Self {
+    pub fn new(lines: &'a [LogLine], rows: &'a [usize], selected: HashMap<usize, Vec<bool>>) -> Self {
         Self {
             lines,
+            rows,
             start: 0,
             line_start: 0,
             highlighted: 0,
             selected,
+            near_misses: None,
+            search: None,
         }
     }
 
@@ -90,6 +104,16 @@ impl<'a> LogFileWdiget<'a> {
         self.line_start = line_start;
         self
     }
+
+    pub fn near_misses(mut self, near_misses: &'a HashMap<usize, Vec<bool>>) -> Self {
+        self.near_misses = Some(near_misses);
+        self
+    }
+
+    pub fn search(mut self, search: Option<&'a Regex>) -> Self {
+        self.search = search;
+        self
+    }
 }
 
 impl Widget for LogFileWdiget<'_> {
@@ -98,12 +122,15 @@ impl Widget for LogFileWdiget<'_> {
         Self: Sized,
     {
         for i in 0..area.height {
-            let index = i as usize + self.start;
-            if index >= self.lines.len() {
+            let row = i as usize + self.start;
+            if row >= self.rows.len() {
                 break;
             }
+            let index = self.rows[row];
+            // the number of lines hidden after this one by a folded section
+            let folded = self.rows.get(row + 1).copied().unwrap_or(self.lines.len()) - index - 1;
 
-            let style = if self.highlighted == index {
+            let style = if self.highlighted == row {
                 Style::new().bold().underlined()
             } else {
                 Style::new()
@@ -121,44 +148,55 @@ impl Widget for LogFileWdiget<'_> {
                 style
             };
 
-            let text: String = self.lines[index].chars().skip(self.line_start).collect();
-            let line = Line::from(vec![
+            let text_style = if selection[0] {
+                style.bg(Color::Blue)
+            } else {
+                style
+            };
+            let symbols_style = if selection.iter().all(|b| *b) {
+                style.fg(Color::Yellow)
+            } else {
+                style
+            };
+            let near_misses = self.near_misses.and_then(|n| n.get(&index));
+            let mut spans = vec![
                 Span::styled(
                     format!("{:1$}", index, self.lines.len().to_string().chars().count()),
                     style.fg(Color::DarkGray),
                 ),
-                if self.highlighted == index {
+                if self.highlighted == row {
                     Span::styled(" > ", style)
+                } else if folded > 0 {
+                    Span::styled(" ▸ ", style)
                 } else {
                     Span::styled("   ", style)
                 },
-                Span::styled(
-                    [
-                        if selection[1] { "☘" } else { " " },
-                        if selection[2] { "⚐" } else { " " },
-                        if selection[3] { "⚙" } else { " " },
-                        if selection[4] { "⚷" } else { " " },
-                        if selection[5] { "☍" } else { " " },
-                        if selection[6] { "⛆" } else { " " },
-                    ]
-                    .join(""),
-                    if selection.iter().all(|b| *b) {
-                        style.fg(Color::Yellow)
-                    } else {
-                        style
-                    },
-                ),
-                Span::styled(" ", style),
-                Span::styled(
-                    &text,
-                    if selection[0] {
-                        style.bg(Color::Blue)
-                    } else {
-                        style
-                    },
-                ),
-            ]);
-            buf.set_line(area.x, area.y + i, &line, area.width);
+            ];
+            for (i, symbol) in ["☘", "⚐", "⚙", "⚷", "☍", "⛆"].into_iter().enumerate() {
+                let i = i + 1;
+                spans.push(match near_misses {
+                    Some(near_misses) if near_misses[i] => Span::styled(symbol, style.fg(Color::Magenta)),
+                    _ if selection[i] => Span::styled(symbol, symbols_style),
+                    _ => Span::styled(" ", symbols_style),
+                });
+            }
+            spans.push(Span::styled(" ", style));
+            for (part, matched) in
+                search::split_matches(self.search, &self.lines[index].content, self.line_start)
+            {
+                spans.push(if matched {
+                    Span::styled(part, text_style.bg(Color::Yellow).fg(Color::Black))
+                } else {
+                    Span::styled(part, text_style)
+                });
+            }
+            if folded > 0 {
+                spans.push(Span::styled(
+                    format!(" [{} folded lines]", folded),
+                    style.fg(Color::DarkGray),
+                ));
+            }
+            buf.set_line(area.x, area.y + i, &Line::from(spans), area.width);
         }
     }
 }
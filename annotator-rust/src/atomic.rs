@@ -0,0 +1,34 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::Context;
+
+use crate::Result;
+
+/// Write the content to the file atomically: it is written to a temporary file next to it, which
+/// then replaces it. The file is never left half written (e.g. when the disk is full or when the
+/// program is killed), it either has its old or its new content.
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, content: C) -> Result<()> {
+    let path = path.as_ref();
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} is not a file", path.display()))?;
+    let mut temporary_name = OsString::from(".");
+    temporary_name.push(file_name);
+    temporary_name.push(".tmp");
+    let temporary = path.with_file_name(temporary_name);
+    let written = File::create(&temporary)
+        .and_then(|mut file| {
+            file.write_all(content.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written.with_context(|| format!("could not write {}", path.display()))
+}
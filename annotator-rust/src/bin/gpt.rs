@@ -1,9 +1,8 @@
 use std::{
     env,
-    error::Error,
     fs::{self, File},
     io::{BufWriter, Write},
-    path::{Path, PathBuf},
+    path::PathBuf,
     thread,
     time::Duration,
 };
@@ -16,48 +15,38 @@ use async_openai::{
     },
     Client,
 };
+use anyhow::Context;
 use chrono::Local;
+use cidiff_annotations::{
+    dataset::{self, DatasetArgs},
+    manifest,
+    Dataset, DatasetIndex, LogFormat, LogLine, LogParser, Result,
+};
 use clap::Parser;
 use indicatif::{ProgressBar, ProgressStyle};
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 #[derive(Debug, Parser)]
 struct Args {
     dataset: String,
+    /// The manifest (`sample.toml`) or the file listing the pairs to annotate
     logs_file: String,
     #[arg(short, long = "dry")]
     dry_run: bool,
     #[arg(short, long)]
     fixed: Option<String>,
-}
-
-fn cidiff_gh_parse(file_content: String) -> Vec<(usize, String)> {
-    let timestamp = Regex::new(r"(?:\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}.\d{7}Z ?)?(.*)").unwrap();
-    let ansi = Regex::new(r"\x1b?\[(?:\d+)?(?:;\d+)*m").unwrap();
-    let mut v = vec![];
-    let mut i = 0;
-    for ele in file_content.lines() {
-        let caps = timestamp.captures(ele).unwrap();
-        let content = &caps[1];
-        let cleaned = ansi.replace_all(content, "");
-        if !cleaned.trim().is_empty() {
-            v.push((i, cleaned.to_string()));
-            i += 1;
-        }
-        //v.push(format!("$${}$$ {}", i, cleaned).to_string());
-    }
-    //println!("log:\n{:?}", v);
-    //println!("bytes 268: {:?}", v[268].1.chars());
-    //v.join("\n")
-    v
+    /// The format of the logs. By default, it is detected from the first lines of each log
+    #[arg(long, value_enum, default_value_t = LogFormat::Auto)]
+    format: LogFormat,
+    #[command(flatten)]
+    dataset_args: DatasetArgs,
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() -> Result<()> {
     dotenv::dotenv().ok();
-    let openai_key = env::var("OPENAI_KEY").expect("The OpenAI key is not in the environment variables");
+    let openai_key = env::var("OPENAI_KEY").context("The OpenAI key is not in the environment variables")?;
 
     let args = Args::parse();
 
@@ -65,23 +54,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
         println!("Running in dry run");
     }
 
-    let dataset_path = Path::new(&args.dataset);
+    let dataset = Dataset::new(&args.dataset, args.dataset_args.spec()?);
 
     let config = OpenAIConfig::new().with_api_key(openai_key);
     let client = Client::with_config(config);
 
+    let parser = LogParser::new().format(args.format);
+
     if let Some(fixed) = args.fixed {
-        do_simple(&client, fixed, args.dry_run).await;
-        return Ok(());
+        return do_simple(&client, &parser, fixed, args.dry_run).await;
     }
 
-    let binding = fs::read_to_string(args.logs_file).expect("Unable to read the logs file");
-    let paths: Vec<PathBuf> = binding
-        .lines()
-        .map(PathBuf::from)
-        //.map(Path::new)
-        //.map(|p| p.join("failure.log"))
-        .collect();
+    let (index, diagnostics) = DatasetIndex::open(&dataset)?;
+    dataset::print_diagnostics(&diagnostics);
+    let paths: Vec<PathBuf> = manifest::read_pairs(&args.logs_file, &dataset, &parser, &index)?;
     let bar = ProgressBar::new(paths.len() as u64)
         .with_style(ProgressStyle::with_template("[{pos}/{len}] {msg} {wide_bar}").unwrap());
     //let multi = MultiProgress::new();
@@ -101,7 +87,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
         "{}/gpt.csv",
         output_dir.to_str().unwrap(),
     ))?);
-    writeln!(result, "path,type,line")?;
+    // gpt answers the most relevant lines first
+    writeln!(result, "path,type,line,rank")?;
     //result.flush()?;
 
     for path in paths {
@@ -111,19 +98,21 @@ async fn main() -> Result<(), Box<dyn Error>> {
         let log_bar = ProgressBar::new_spinner();
         log_bar.enable_steady_tick(Duration::from_millis(100));
 
-        let log_path = dataset_path.join(&path).join("failure.log");
+        let log_path = dataset.failure_log(&path);
         log_bar.set_message(format!(
             "Reading the log file {}",
             log_path.to_str().unwrap_or("<err>")
         ));
-        let log_content = cidiff_gh_parse(fs::read_to_string(&log_path).unwrap());
+        let log_content = parser
+            .parse_file(&log_path)
+            .with_context(|| format!("could not read {}", log_path.display()))?;
 
         log_bar.set_message("Request sent to chatgpt, awaiting response");
         if let Some(response) = ask_gpt(
             &client,
             log_content
                 .iter()
-                .map(|(i, s)| format!("$${}$$ {}", i, s))
+                .map(|line| format!("$${}$$ {}", line.number, line.content))
                 .collect::<Vec<String>>()
                 .join("\n"),
             args.dry_run,
@@ -144,11 +133,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 Ok(resp) => {
                     println!("checking truth for {}", log_path.to_str().unwrap());
                     check_truth(&resp, &log_content);
+                    let mut rank = 0;
                     for line in resp.lines {
                         if line.contains("$$") {
                             match line.split("$$").collect::<Vec<&str>>()[1].parse::<usize>() {
                                 Ok(i) => {
-                                    writeln!(result, "{},gpt,{}", path.to_str().unwrap(), i)?;
+                                    rank += 1;
+                                    writeln!(result, "{},gpt,{},{}", path.to_str().unwrap(), i, rank)?;
                                 }
                                 Err(e) => {
                                     eprintln!(
@@ -188,14 +179,14 @@ struct Resp {
     lines: Vec<String>,
     steps: Vec<String>,
 }
-fn check_truth(response: &Resp, log_content: &[(usize, String)]) {
+fn check_truth(response: &Resp, log_content: &[LogLine]) {
     response
         .lines
         .iter()
         .enumerate()
-        .filter(|(i, line)| **line == log_content[*i].1)
-        //.map(|(i, line)| *line == log_content[i].1)
-        .for_each(|(i, line)| println!("line {} {} is not {}", i, line, log_content[i].1));
+        .filter(|(i, line)| **line == log_content[*i].content)
+        //.map(|(i, line)| *line == log_content[i].content)
+        .for_each(|(i, line)| println!("line {} {} is not {}", i, line, log_content[i].content));
     //.collect();
 }
 
@@ -305,11 +296,13 @@ async fn ask_gpt(client: &Client<OpenAIConfig>, log_content: String, dry_run: bo
 //     "additionalProperties": false
 // });
 
-async fn do_simple(client: &Client<OpenAIConfig>, fixed: String, dry_run: bool) {
+async fn do_simple(client: &Client<OpenAIConfig>, parser: &LogParser, fixed: String, dry_run: bool) -> Result<()> {
     let spinner = ProgressBar::new_spinner();
     spinner.enable_steady_tick(Duration::from_millis(100));
     spinner.set_message("Request sent to chatgpt, awaiting response");
-    let log_content = cidiff_gh_parse(fs::read_to_string(&fixed).unwrap());
+    let log_content = parser
+        .parse_file(&fixed)
+        .with_context(|| format!("could not read {}", fixed))?;
 
     let output_dir: PathBuf = [
         "./generated",
@@ -325,7 +318,7 @@ async fn do_simple(client: &Client<OpenAIConfig>, fixed: String, dry_run: bool)
         client,
         log_content
             .iter()
-            .map(|(i, s)| format!("$${}$$ {}", i, s))
+            .map(|line| format!("$${}$$ {}", line.number, line.content))
             .collect::<Vec<String>>()
             .join("\n"),
         dry_run,
@@ -344,7 +337,9 @@ async fn do_simple(client: &Client<OpenAIConfig>, fixed: String, dry_run: bool)
         };
         spinner.finish();
         println!("checking truth for {}", fixed);
-        let resp: Resp = serde_json::from_str(&response).unwrap();
+        let resp: Resp = serde_json::from_str(&response)
+            .with_context(|| format!("the answer for {} is not in the expected format", fixed))?;
         check_truth(&resp, &log_content);
     }
+    Ok(())
 }
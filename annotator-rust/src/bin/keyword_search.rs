@@ -1,17 +1,26 @@
 use std::{
-    fs::{self, File},
-    io::{BufWriter, Result, Write},
-    path::PathBuf,
+    fs::File,
+    io::{BufWriter, Write},
 };
 
+use anyhow::Context;
+use cidiff_annotations::{
+    dataset::{self, DatasetArgs},
+    manifest, Dataset, DatasetIndex, LogFormat, LogParser, Result,
+};
 use clap::Parser;
 use indicatif::{ProgressBar, ProgressStyle};
-use regex::Regex;
 
 #[derive(Debug, Parser)]
 struct Args {
     dataset: String,
+    /// The manifest (`sample.toml`) or the file listing the pairs to annotate
     logs_file: String,
+    /// The format of the logs. By default, it is detected from the first lines of each log
+    #[arg(long, value_enum, default_value_t = LogFormat::Auto)]
+    format: LogFormat,
+    #[command(flatten)]
+    dataset_args: DatasetArgs,
 }
 
 const KEYWORDS: [&str; 6] = ["failure", "fail", "failed", "error", "exception", "panic"];
@@ -20,24 +29,28 @@ const KEYWORDS: [&str; 6] = ["failure", "fail", "failed", "error", "exception",
 /// i.e. if a specific keyword is present in the line, then the line is considered useful.
 fn main() -> Result<()> {
     let args = Args::parse();
-    let logs_file_content = fs::read_to_string(args.logs_file).expect("Unable to read the logs file");
-    let paths: Vec<_> = logs_file_content.lines().map(PathBuf::from).collect();
+    let dataset = Dataset::new(&args.dataset, args.dataset_args.spec()?);
+    let (index, diagnostics) = DatasetIndex::open(&dataset)?;
+    dataset::print_diagnostics(&diagnostics);
+    let parser = LogParser::new().format(args.format);
+    let paths = manifest::read_pairs(&args.logs_file, &dataset, &parser, &index)?;
     let bar = ProgressBar::new(paths.len() as u64)
         .with_style(ProgressStyle::with_template("[{pos}/{len}] {msg} {wide_bar}").unwrap());
-    let dataset_path = PathBuf::from(args.dataset);
     let mut output = BufWriter::new(File::create("keyword.csv")?);
     writeln!(output, "path,type,line")?;
     for path in paths {
         let s = path.to_str().unwrap();
         bar.inc(1);
         bar.set_message(path.to_str().unwrap().to_string());
-        let log_path = dataset_path.join(&path).join("failure.log");
-        let log_content = parse_file(fs::read_to_string(&log_path).unwrap());
-        for (i, line) in log_content.iter().enumerate() {
-            let lower = line.to_lowercase();
+        let log_path = dataset.failure_log(&path);
+        let log_content = parser
+            .parse_file(&log_path)
+            .with_context(|| format!("could not read {}", log_path.display()))?;
+        for line in log_content.iter() {
+            let lower = line.content.to_lowercase();
             for keyword in KEYWORDS {
                 if lower.contains(keyword) {
-                    writeln!(output, "{},keyword,{}", s, i)?;
+                    writeln!(output, "{},keyword,{}", s, line.number)?;
                     break;
                 }
             }
@@ -49,20 +62,3 @@ fn main() -> Result<()> {
 
     Ok(())
 }
-
-/// Parse the file. By default, we remove the github timestamp at the begining of each line, and
-/// remove any ANSI escape code
-pub fn parse_file(file_content: String) -> Vec<String> {
-    let timestamp_regex = Regex::new(r"(?:\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}.\d{7}Z ?)?(.*)").unwrap();
-    let ansi_color_regex = Regex::new(r"\x1b?\[(?:\d+)?(?:;\d+)*m").unwrap();
-    let mut lines = vec![];
-    for line in file_content.lines() {
-        let caps = timestamp_regex.captures(line).unwrap();
-        let content = &caps[1];
-        let cleaned = ansi_color_regex.replace_all(content, "");
-        if !cleaned.trim().is_empty() {
-            lines.push(cleaned.to_string());
-        }
-    }
-    lines
-}
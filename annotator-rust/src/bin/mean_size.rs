@@ -1,6 +1,12 @@
 use std::{fs, path::Path};
 
-use clap::{command, Parser};
+use anyhow::Context;
+use cidiff_annotations::{
+    dataset::{self, DatasetArgs},
+    manifest::{self, MANIFEST_FILE},
+    Dataset, DatasetIndex, LogFormat, LogParser, Result,
+};
+use clap::Parser;
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
@@ -8,20 +14,48 @@ use clap::{command, Parser};
 struct Args {
     /// The path of the dataset
     dataset: String,
+    /// Count the words once the logs are parsed (without the escape sequences nor the prefixes of
+    /// the CI) instead of in the files
+    #[arg(long)]
+    parsed: bool,
+    /// The format of the logs with --parsed. By default, it is detected from the first lines of
+    /// each log
+    #[arg(long, value_enum, default_value_t = LogFormat::Auto)]
+    format: LogFormat,
+    #[command(flatten)]
+    dataset_args: DatasetArgs,
+    /// The manifest (or the list) of the pairs to measure
+    #[arg(short, long, default_value = MANIFEST_FILE)]
+    paths: String,
 }
 
-fn main() -> std::io::Result<()> {
+fn main() -> Result<()> {
     let args = Args::parse();
 
-    let dataset_path = Path::new(&args.dataset);
+    let dataset = Dataset::new(&args.dataset, args.dataset_args.spec()?);
+    let (index, diagnostics) = DatasetIndex::open(&dataset)?;
+    dataset::print_diagnostics(&diagnostics);
+    let parser = LogParser::new().format(args.format);
+    let count_words = |path: &Path| -> Result<usize> {
+        Ok(if args.parsed {
+            let lines = parser
+                .parse_file(path)
+                .with_context(|| format!("could not read {}", path.display()))?;
+            lines.iter().map(|l| l.content.split_whitespace().count()).sum()
+        } else {
+            fs::read_to_string(path)
+                .with_context(|| format!("could not read {}", path.display()))?
+                .split_whitespace()
+                .count()
+        })
+    };
     let mut v = vec![];
     let mut v2 = vec![];
-    for path in fs::read_to_string(Path::new("paths.txt"))?.lines() {
-        let p = dataset_path.join(path).join("failure.log");
-        let c = fs::read_to_string(p)?.split_whitespace().count();
+    let paths = manifest::read_pairs(&args.paths, &dataset, &parser, &index)?;
+    for path in paths.iter() {
+        let c = count_words(&dataset.failure_log(path))?;
         v.push(c);
-        let p2 = dataset_path.join(path).join("success.log");
-        let c2 = fs::read_to_string(p2)?.split_whitespace().count();
+        let c2 = count_words(&dataset.success_log(path))?;
         v2.push(c2);
     }
 
@@ -0,0 +1,26 @@
+use anyhow::{anyhow, bail};
+use cidiff_annotations::Result;
+use copypasta::{ClipboardContext, ClipboardProvider};
+
+/// The system clipboard. It is not available everywhere (e.g. on a headless machine), copying is
+/// then an error shown to the user instead of a crash at start.
+pub struct Clipboard {
+    context: Result<ClipboardContext, String>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self {
+            context: ClipboardContext::new().map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn copy(&mut self, text: String) -> Result<()> {
+        match &mut self.context {
+            Ok(context) => context
+                .set_contents(text)
+                .map_err(|e| anyhow!("could not copy to the clipboard: {}", e)),
+            Err(e) => bail!("no clipboard available ({})", e),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{index::IndexEntry, Result};
+
+/// What to do with the symbolic links found in the dataset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// Follow the links, as if they were the files/directories they point to
+    #[default]
+    Follow,
+    /// Ignore the links
+    Skip,
+}
+
+/// The rules to find the log pairs in the dataset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatasetSpec {
+    /// The name of the log of the successful run, in each pair directory
+    pub success_file: String,
+    /// The name of the log of the failed run, in each pair directory
+    pub failure_file: String,
+    pub min_success_lines: Option<usize>,
+    pub max_success_lines: Option<usize>,
+    pub min_failure_lines: Option<usize>,
+    pub max_failure_lines: Option<usize>,
+    /// Only keep the pairs whose path (relative to the dataset) matches one of these globs. Every
+    /// pair is kept when empty.
+    pub include: Vec<String>,
+    /// Ignore the directories whose path (relative to the dataset) matches one of these globs
+    pub exclude: Vec<String>,
+    pub symlinks: SymlinkPolicy,
+    /// A csv file giving the language of the repositories, with (at least) a `repository` and a
+    /// `language` column, like `csv/dataset.csv`
+    pub languages: Option<PathBuf>,
+}
+
+impl Default for DatasetSpec {
+    /// The rules of the experiment: pairs of `success.log`/`failure.log` with a failure log of
+    /// 1000 lines maximum.
+    fn default() -> Self {
+        Self {
+            success_file: String::from("success.log"),
+            failure_file: String::from("failure.log"),
+            min_success_lines: None,
+            max_success_lines: None,
+            min_failure_lines: None,
+            max_failure_lines: Some(1000),
+            include: vec![],
+            exclude: vec![],
+            symlinks: SymlinkPolicy::Follow,
+            languages: None,
+        }
+    }
+}
+
+/// The command line options to build a [`DatasetSpec`]. The options override the values of the
+/// spec file.
+#[derive(Debug, Clone, Default, Args)]
+// the doc comment is not the description of the binaries
+#[command(about = None, long_about = None)]
+pub struct DatasetArgs {
+    /// A toml file with the rules to find the log pairs in the dataset
+    #[arg(long)]
+    pub dataset_spec: Option<PathBuf>,
+    /// The name of the log of the successful run [default: success.log]
+    #[arg(long)]
+    pub success_file: Option<String>,
+    /// The name of the log of the failed run [default: failure.log]
+    #[arg(long)]
+    pub failure_file: Option<String>,
+    #[arg(long)]
+    pub min_success_lines: Option<usize>,
+    #[arg(long)]
+    pub max_success_lines: Option<usize>,
+    #[arg(long)]
+    pub min_failure_lines: Option<usize>,
+    /// [default: 1000]
+    #[arg(long)]
+    pub max_failure_lines: Option<usize>,
+    /// Only keep the pairs whose path matches this glob (can be repeated)
+    #[arg(long)]
+    pub include: Vec<String>,
+    /// Ignore the directories whose path matches this glob (can be repeated)
+    #[arg(long)]
+    pub exclude: Vec<String>,
+    /// What to do with the symbolic links [default: follow]
+    #[arg(long, value_enum)]
+    pub symlinks: Option<SymlinkPolicy>,
+    /// A csv file giving the language of the repositories (e.g. `csv/dataset.csv`)
+    #[arg(long)]
+    pub languages: Option<PathBuf>,
+}
+
+impl DatasetArgs {
+    /// The spec of the spec file (or the default one), overridden by the options.
+    pub fn spec(&self) -> Result<DatasetSpec> {
+        let mut spec = match &self.dataset_spec {
+            Some(path) => DatasetSpec::from_file(path)?,
+            None => DatasetSpec::default(),
+        };
+        if let Some(success_file) = &self.success_file {
+            spec.success_file = success_file.clone();
+        }
+        if let Some(failure_file) = &self.failure_file {
+            spec.failure_file = failure_file.clone();
+        }
+        spec.min_success_lines = self.min_success_lines.or(spec.min_success_lines);
+        spec.max_success_lines = self.max_success_lines.or(spec.max_success_lines);
+        spec.min_failure_lines = self.min_failure_lines.or(spec.min_failure_lines);
+        spec.max_failure_lines = self.max_failure_lines.or(spec.max_failure_lines);
+        spec.include.extend(self.include.iter().cloned());
+        spec.exclude.extend(self.exclude.iter().cloned());
+        spec.symlinks = self.symlinks.unwrap_or(spec.symlinks);
+        if let Some(languages) = &self.languages {
+            spec.languages = Some(languages.clone());
+        }
+        Ok(spec)
+    }
+}
+
+/// The dataset given to the tools, with the rules to read it.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub path: PathBuf,
+    pub spec: DatasetSpec,
+}
+
+impl Dataset {
+    pub fn new<P: AsRef<Path>>(path: P, spec: DatasetSpec) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            spec,
+        }
+    }
+
+    /// The path of the log of the successful run of a pair
+    pub fn success_log<P: AsRef<Path>>(&self, pair: P) -> PathBuf {
+        self.path.join(pair).join(&self.spec.success_file)
+    }
+
+    /// The path of the log of the failed run of a pair
+    pub fn failure_log<P: AsRef<Path>>(&self, pair: P) -> PathBuf {
+        self.path.join(pair).join(&self.spec.failure_file)
+    }
+
+    /// List the pairs of the dataset, see [`DatasetSpec::visit`].
+    pub fn visit(&self, cb: &mut dyn FnMut(&Path)) -> Result<Walk> {
+        self.spec.visit(&self.path, cb)
+    }
+}
+
+/// An entry of the dataset that could not be read, and was ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// Print the entries of the dataset that were ignored.
+pub fn print_diagnostics(diagnostics: &[Diagnostic]) {
+    if !diagnostics.is_empty() {
+        eprintln!("{} entries of the dataset were ignored:", diagnostics.len());
+        for diagnostic in diagnostics {
+            eprintln!("  {}", diagnostic);
+        }
+    }
+}
+
+/// The result of a walk through the dataset.
+#[derive(Debug, Clone, Default)]
+pub struct Walk {
+    /// The directories listed during the walk (relative to the dataset), which are not pairs. A
+    /// pair can only be added or removed by changing one of them.
+    pub directories: Vec<PathBuf>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl DatasetSpec {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content =
+            fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("{} is not a valid dataset spec", path.display()))
+    }
+
+    fn glob_set(globs: &[String]) -> Result<GlobSet> {
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs {
+            builder.add(Glob::new(glob)?);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Visit every directory of the dataset recursively and call the callback with the path
+    /// (relative to the dataset) of each directory containing a log pair matching the file names
+    /// and the globs. The line limits are not checked here, see [`DatasetSpec::accepts`]. The
+    /// entries that can not be read are returned as diagnostics.
+    pub fn visit(&self, dataset_path: &Path, cb: &mut dyn FnMut(&Path)) -> Result<Walk> {
+        let mut visitor = Visitor {
+            spec: self,
+            dataset_path,
+            include: Self::glob_set(&self.include)?,
+            exclude: Self::glob_set(&self.exclude)?,
+            visited: HashSet::new(),
+            walk: Walk::default(),
+        };
+        visitor.visit_dirs(dataset_path, cb);
+        Ok(visitor.walk)
+    }
+
+    /// Whether two specs find the same pairs in a dataset (they may still have different line
+    /// limits).
+    pub fn same_pairs(&self, other: &DatasetSpec) -> bool {
+        self.success_file == other.success_file
+            && self.failure_file == other.failure_file
+            && self.include == other.include
+            && self.exclude == other.exclude
+            && self.symlinks == other.symlinks
+    }
+
+    /// Whether the number of lines of the logs of the pair are in the limits.
+    pub fn accepts(&self, entry: &IndexEntry) -> bool {
+        let limits = [
+            (
+                entry.success.lines,
+                self.min_success_lines,
+                self.max_success_lines,
+            ),
+            (
+                entry.failure.lines,
+                self.min_failure_lines,
+                self.max_failure_lines,
+            ),
+        ];
+        limits
+            .into_iter()
+            .all(|(n, min, max)| (min.unwrap_or(0)..=max.unwrap_or(usize::MAX)).contains(&n))
+    }
+}
+
+struct Visitor<'a> {
+    spec: &'a DatasetSpec,
+    dataset_path: &'a Path,
+    include: GlobSet,
+    exclude: GlobSet,
+    /// The canonical paths of the directories already visited, to not loop on symbolic links
+    visited: HashSet<PathBuf>,
+    walk: Walk,
+}
+
+impl Visitor<'_> {
+    fn report(&mut self, path: &Path, message: impl ToString) {
+        self.walk.diagnostics.push(Diagnostic {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+        });
+    }
+
+    fn is_log(&self, path: &Path) -> bool {
+        match self.spec.symlinks {
+            SymlinkPolicy::Follow => path.is_file(),
+            // the metadata of a link is never a file
+            SymlinkPolicy::Skip => path.symlink_metadata().map(|m| m.is_file()).unwrap_or(false),
+        }
+    }
+
+    fn visit_dirs(&mut self, dir: &Path, cb: &mut dyn FnMut(&Path)) {
+        match fs::canonicalize(dir) {
+            Ok(canonical) => {
+                if !self.visited.insert(canonical) {
+                    self.report(dir, "already visited (symbolic link loop?)");
+                    return;
+                }
+            }
+            Err(e) => return self.report(dir, e),
+        }
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => return self.report(dir, e),
+        };
+        self.walk
+            .directories
+            .push(dir.strip_prefix(self.dataset_path).unwrap_or(dir).to_path_buf());
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.report(dir, e);
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let is_symlink = entry.file_type().map(|t| t.is_symlink()).unwrap_or(false);
+            if (is_symlink && self.spec.symlinks == SymlinkPolicy::Skip) || !path.is_dir() {
+                continue;
+            }
+            let relative = path
+                .strip_prefix(self.dataset_path)
+                .unwrap_or(&path)
+                .to_path_buf();
+            if self.exclude.is_match(&relative) {
+                continue;
+            }
+            if self.is_log(&path.join(&self.spec.success_file))
+                && self.is_log(&path.join(&self.spec.failure_file))
+            {
+                if self.include.is_empty() || self.include.is_match(&relative) {
+                    cb(&relative);
+                }
+            } else {
+                self.visit_dirs(&path, cb);
+            }
+        }
+    }
+}
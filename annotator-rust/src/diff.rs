@@ -0,0 +1,138 @@
+//! The alignment of the lines of two logs (the success and the failure log of a pair), from the
+//! longest common subsequence of their lines, to show them side by side.
+
+use std::{collections::HashMap, hash::Hash};
+
+/// The alignment of an old log (e.g. the success log) and a new log (e.g. the failure log).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alignment {
+    /// The line of the old log matching each line of the new log, if any
+    pub old_of_new: Vec<Option<usize>>,
+    /// The line of the new log matching each line of the old log, if any
+    pub new_of_old: Vec<Option<usize>>,
+}
+
+impl Alignment {
+    /// Align the lines of two logs: the matching lines are the ones of their longest common
+    /// subsequence.
+    pub fn new<T: Eq + Hash>(old: &[T], new: &[T]) -> Self {
+        let mut old_of_new = vec![None; new.len()];
+        let mut new_of_old = vec![None; old.len()];
+        for (o, n) in lcs(old, new) {
+            old_of_new[n] = Some(o);
+            new_of_old[o] = Some(n);
+        }
+        Self {
+            old_of_new,
+            new_of_old,
+        }
+    }
+
+    /// The line of the old log to show next to a line of the new log: the matching line, or the
+    /// line at the same place in the hunk of changed lines around it (the last line of the hunk
+    /// when it is shorter). It is the line before the hunk when the hunk has no old line, and
+    /// `None` when the old log is empty.
+    pub fn counterpart(&self, new_line: usize) -> Option<usize> {
+        if let Some(old) = self.old_of_new.get(new_line).copied().flatten() {
+            return Some(old);
+        }
+        let before = self.old_of_new[..new_line.min(self.old_of_new.len())]
+            .iter()
+            .rposition(Option::is_some);
+        // the old lines of the hunk, between the matching lines before and after it
+        let first = before.and_then(|n| self.old_of_new[n]).map_or(0, |o| o + 1);
+        let end = self
+            .old_of_new
+            .iter()
+            .skip(new_line)
+            .find_map(|o| *o)
+            .unwrap_or(self.new_of_old.len());
+        let offset = new_line - before.map_or(0, |n| n + 1);
+        if first < end {
+            Some((first + offset).min(end - 1))
+        } else {
+            first
+                .checked_sub(1)
+                .or((end < self.new_of_old.len()).then_some(end))
+        }
+    }
+}
+
+/// The longest common subsequence of two sequences, as the pairs of indexes of its elements in
+/// each sequence (in increasing order).
+///
+/// It takes a linear memory (Hirschberg's algorithm), as the logs can have thousands of lines.
+pub fn lcs<T: Eq + Hash>(old: &[T], new: &[T]) -> Vec<(usize, usize)> {
+    // the elements are compared a lot: compare small identifiers instead
+    let mut ids = HashMap::new();
+    let mut id = |element| {
+        let next = ids.len();
+        *ids.entry(element).or_insert(next)
+    };
+    let old = old.iter().map(&mut id).collect::<Vec<_>>();
+    let new = new.iter().map(&mut id).collect::<Vec<_>>();
+
+    // the common prefix and suffix are always in the subsequence
+    let prefix = old.iter().zip(&new).take_while(|(o, n)| o == n).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(o, n)| o == n)
+        .count();
+    let mut pairs = (0..prefix).map(|i| (i, i)).collect::<Vec<_>>();
+    hirschberg(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+        (prefix, prefix),
+        &mut pairs,
+    );
+    pairs.extend((0..suffix).map(|i| (old.len() - suffix + i, new.len() - suffix + i)));
+    pairs
+}
+
+fn hirschberg(old: &[usize], new: &[usize], offset: (usize, usize), pairs: &mut Vec<(usize, usize)>) {
+    if old.is_empty() || new.is_empty() {
+        return;
+    }
+    if old.len() == 1 {
+        if let Some(n) = new.iter().position(|n| *n == old[0]) {
+            pairs.push((offset.0, offset.1 + n));
+        }
+        return;
+    }
+    // split the new sequence where the subsequence of each half of the old one meet
+    let middle = old.len() / 2;
+    let before = lcs_lengths(old[..middle].iter(), new.iter());
+    let after = lcs_lengths(old[middle..].iter().rev(), new.iter().rev());
+    let split = (0..=new.len())
+        .max_by_key(|&n| (before[n] + after[new.len() - n], std::cmp::Reverse(n)))
+        .unwrap_or(0);
+    hirschberg(&old[..middle], &new[..split], offset, pairs);
+    hirschberg(
+        &old[middle..],
+        &new[split..],
+        (offset.0 + middle, offset.1 + split),
+        pairs,
+    );
+}
+
+/// The length of the longest common subsequence of `old` and each prefix of `new`.
+fn lcs_lengths<'a>(
+    old: impl Iterator<Item = &'a usize>,
+    new: impl Iterator<Item = &'a usize> + Clone,
+) -> Vec<usize> {
+    let mut previous = vec![0; new.clone().count() + 1];
+    let mut current = previous.clone();
+    for o in old {
+        for (i, n) in new.clone().enumerate() {
+            current[i + 1] = if o == n {
+                previous[i] + 1
+            } else {
+                current[i].max(previous[i + 1])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous
+}
@@ -0,0 +1,24 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    symbols::border,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph},
+    Frame,
+};
+
+/// Show an error in place of the instructions, until the next key press.
+pub fn draw_error(frame: &mut Frame, error: &str) {
+    let area = frame.area();
+    let area = Rect::new(
+        area.x,
+        area.y + area.height.saturating_sub(3),
+        area.width,
+        area.height.min(3),
+    );
+    let paragraph = Paragraph::new(Line::from(Span::styled(error, Style::default().fg(Color::Red))))
+        .block(Block::bordered().title("Error").border_set(border::THICK))
+        .alignment(Alignment::Center);
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
@@ -0,0 +1,558 @@
+use std::collections::BTreeSet;
+
+use cidiff_annotations::{
+    evaluation::{
+        load_selections, Comparison, Confusion, Evaluation, Evaluations, Metric, RankingMeans, RegionCounts,
+        RegionMeans, Scores, Settings,
+    },
+    statistics::{Interval, Test},
+    Dataset, LogParser, Result,
+};
+use clap::Args;
+
+/// Evaluate the lines selected by the algorithms against the lines selected by the humans, per log
+/// and per type
+#[derive(Debug, Args)]
+pub struct EvaluateArgs {
+    /// The csv files of the selections (`path,type,line`, with an optional `score` or `rank`
+    /// column), the reference and the algorithms
+    #[arg(required = true)]
+    files: Vec<String>,
+    /// The type of the reference: `human` for every annotator, `human:<id>` for one of them, or
+    /// `gold` for the gold set
+    #[arg(short, long, default_value = "human")]
+    reference: String,
+    /// Only consider the lines of the reference selected with this label
+    #[arg(long)]
+    label: Option<String>,
+    /// How many times the recall is as important as the precision in the F-beta score
+    #[arg(short, long, default_value_t = 2.0)]
+    beta: f64,
+    /// Print the scores of each log as csv instead of the table
+    #[arg(long)]
+    csv: bool,
+    /// The number of resamples of the logs for the confidence intervals of the averages (0 for none)
+    #[arg(long, default_value_t = 1000)]
+    resamples: usize,
+    /// The confidence level of the intervals
+    #[arg(long, default_value_t = 0.95)]
+    confidence: f64,
+    /// The seed of the resamplings and of the permutations
+    #[arg(long, default_value_t = 0)]
+    resampling_seed: u64,
+    /// Test the differences between each pair of types instead of printing their scores
+    #[arg(long)]
+    compare: bool,
+    /// The score of each log compared by the paired tests
+    #[arg(long, value_enum, default_value_t = Metric::F1)]
+    metric: Metric,
+    /// The number of random permutations of the paired permutation test
+    #[arg(long, default_value_t = 10000)]
+    permutations: usize,
+    /// Also evaluate each type with a selected line counting as relevant when it is at most this
+    /// many lines away from a relevant line (one selected line per relevant line)
+    #[arg(short, long, default_value_t = 0)]
+    tolerance: usize,
+    /// The numbers of lines read for the precision and the recall of the first lines of the ranking
+    #[arg(short, long, value_delimiter = ',', default_values_t = [5, 10])]
+    cutoffs: Vec<usize>,
+    /// Also break the counts and the scores down by step of the jobs (the top level sections of the
+    /// logs, by name), without tolerance
+    #[arg(long)]
+    by_step: bool,
+}
+
+pub fn evaluate(args: EvaluateArgs, dataset: &Dataset, parser: &LogParser) -> Result<()> {
+    let selections = load_selections(&args.files, &args.reference, args.label.as_deref())?;
+    let settings = Settings {
+        reference: args.reference.clone(),
+        tolerance: args.tolerance,
+        cutoffs: args.cutoffs.clone(),
+        by_step: args.by_step,
+    };
+    let evaluations = Evaluations::of(&selections, &settings, dataset, parser)?;
+    match (args.compare, args.csv) {
+        (false, false) => {
+            print_table(&evaluations.evaluations, &args);
+            print_ranking(&evaluations.evaluations, &args);
+            print_regions(&evaluations.evaluations);
+            if args.by_step {
+                print_steps(&evaluations.evaluations, &args);
+            }
+            Ok(())
+        }
+        (false, true) => print_csv(&evaluations.evaluations, &args),
+        (true, csv) => {
+            let comparisons =
+                evaluations.compare(args.metric, args.beta, args.permutations, args.resampling_seed);
+            if csv {
+                print_comparisons_csv(&comparisons, &args)
+            } else {
+                print_comparisons(&comparisons, &args, evaluations.references.len());
+                Ok(())
+            }
+        }
+    }
+}
+
+fn print_table(evaluations: &[Evaluation], args: &EvaluateArgs) {
+    let logs = evaluations.first().map_or(0, |e| e.logs.len());
+    println!("{} logs with relevant lines\n", logs);
+    println!("{:<20} {:>10} {:>10} {:>10} {:>10}", "", "tp", "fp", "fn", "tn");
+    for evaluation in evaluations {
+        let total = evaluation.confusions().iter().sum::<Confusion>();
+        println!(
+            "{:<20} {:>10} {:>10} {:>10} {:>10}",
+            evaluation.name(),
+            total.true_positives,
+            total.false_positives,
+            total.false_negatives,
+            total.true_negatives
+        );
+    }
+    let intervals = evaluations.iter().map(|e| intervals(e, args)).collect::<Vec<_>>();
+    let width = if args.resamples > 0 { 22 } else { 10 };
+    for (i, (average, scores)) in [
+        ("micro-averaged", Scores::micro as fn(&[Confusion], f64) -> Scores),
+        ("macro-averaged", Scores::macro_average),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if args.resamples > 0 {
+            println!(
+                "\n{} ({}% confidence intervals from {} resamples of the logs):",
+                average,
+                args.confidence * 100.0,
+                args.resamples
+            );
+        } else {
+            println!("\n{}:", average);
+        }
+        println!(
+            "{:<20} {:>w$} {:>w$} {:>w$} {:>w$}",
+            "",
+            "precision",
+            "recall",
+            "f1",
+            format!("f{}", args.beta),
+            w = width
+        );
+        for (evaluation, intervals) in evaluations.iter().zip(&intervals) {
+            let scores = scores(&evaluation.confusions(), args.beta);
+            let cell = |score: f64, j: usize| match intervals {
+                Some(intervals) => format!(
+                    "{:.3} [{:.3}, {:.3}]",
+                    score,
+                    intervals[4 * i + j].low,
+                    intervals[4 * i + j].high
+                ),
+                None => format!("{:.3}", score),
+            };
+            println!(
+                "{:<20} {:>w$} {:>w$} {:>w$} {:>w$}",
+                evaluation.name(),
+                cell(scores.precision, 0),
+                cell(scores.recall, 1),
+                cell(scores.f1, 2),
+                cell(scores.f_beta, 3),
+                w = width
+            );
+        }
+    }
+}
+
+/// Print the means of the ranking scores of each type, without tolerance.
+fn print_ranking(evaluations: &[Evaluation], args: &EvaluateArgs) {
+    println!("\nranking (the lines of the types without score nor rank are read in the order of the log):");
+    let mut header = format!("{:<20}", "");
+    for k in &args.cutoffs {
+        header += &format!(" {:>8} {:>8}", format!("p@{}", k), format!("r@{}", k));
+    }
+    println!(
+        "{} {:>8} {:>8} {:>10} {:>8}",
+        header, "map", "ndcg", "before 1st", "found"
+    );
+    for evaluation in evaluations.iter().filter(|e| e.tolerance == 0) {
+        let means = RankingMeans::of(&evaluation.rankings, args.cutoffs.len());
+        let mut row = format!("{:<20}", evaluation.name());
+        for (precision, recall) in means.precision_at.iter().zip(&means.recall_at) {
+            row += &format!(" {:>8.3} {:>8.3}", precision, recall);
+        }
+        println!(
+            "{} {:>8.3} {:>8.3} {:>10} {:>8}",
+            row,
+            means.mean_average_precision,
+            means.ndcg,
+            means
+                .lines_before_first_relevant
+                .map_or("-".to_string(), |l| format!("{:.1}", l)),
+            format!("{}/{}", means.found, evaluation.rankings.len())
+        );
+    }
+}
+
+/// Print how the regions of the reference are found by each type, without tolerance: every
+/// region counts as much.
+fn print_regions(evaluations: &[Evaluation]) {
+    println!("\nregions (the consecutive relevant lines):");
+    println!(
+        "{:<20} {:>8} {:>8} {:>10} {:>10} {:>14}",
+        "", "regions", "hits", "hit rate", "coverage", "fragmentation"
+    );
+    for evaluation in evaluations.iter().filter(|e| e.tolerance == 0) {
+        let total = evaluation.regions.iter().sum::<RegionCounts>();
+        println!(
+            "{:<20} {:>8} {:>8} {:>10.3} {:>10.3} {:>14}",
+            evaluation.name(),
+            total.regions,
+            total.hits,
+            total.hit_rate(),
+            total.coverage(),
+            total
+                .fragmentation()
+                .map_or("-".to_string(), |f| format!("{:.2}", f))
+        );
+    }
+}
+
+/// Print the counts and the scores of each type in each step of the logs (with some relevant or
+/// selected lines), without tolerance: the lines of the steps with the same name are counted
+/// together.
+fn print_steps(evaluations: &[Evaluation], args: &EvaluateArgs) {
+    println!("\nsteps (the top level sections of the logs):");
+    let evaluations = evaluations
+        .iter()
+        .filter(|e| e.tolerance == 0)
+        .collect::<Vec<_>>();
+    let steps = evaluations
+        .iter()
+        .flat_map(|e| e.steps.iter())
+        .filter(|(_, c)| c.true_positives + c.false_positives + c.false_negatives > 0)
+        .map(|(step, _)| step)
+        .collect::<BTreeSet<_>>();
+    for step in steps {
+        println!("{}", step);
+        println!(
+            "  {:<18} {:>6} {:>6} {:>6} {:>10} {:>10} {:>10} {:>10}",
+            "",
+            "tp",
+            "fp",
+            "fn",
+            "precision",
+            "recall",
+            "f1",
+            format!("f{}", args.beta)
+        );
+        for evaluation in &evaluations {
+            let confusion = evaluation.steps.get(step).copied().unwrap_or_default();
+            let scores = Scores::of(&confusion, args.beta);
+            println!(
+                "  {:<18} {:>6} {:>6} {:>6} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+                evaluation.name(),
+                confusion.true_positives,
+                confusion.false_positives,
+                confusion.false_negatives,
+                scores.precision,
+                scores.recall,
+                scores.f1,
+                scores.f_beta
+            );
+        }
+    }
+}
+
+/// The columns of the csv of the regions of a log, or of the regions of every log together.
+fn region_columns(counts: &RegionCounts) -> Vec<String> {
+    vec![
+        counts.regions.to_string(),
+        counts.hits.to_string(),
+        counts.hit_rate().to_string(),
+        counts.coverage().to_string(),
+        counts.fragmentation().map(|f| f.to_string()).unwrap_or_default(),
+    ]
+}
+
+/// The columns of the csv of the mean of the region scores of each log.
+fn mean_region_columns(means: &RegionMeans) -> Vec<String> {
+    vec![
+        means.regions.to_string(),
+        means.hits.to_string(),
+        means.hit_rate.to_string(),
+        means.coverage.to_string(),
+        means.fragmentation.map(|f| f.to_string()).unwrap_or_default(),
+    ]
+}
+
+/// The columns of the csv of the means of the ranking scores, from the precision and the recall at
+/// each cutoff.
+fn ranking_columns_of(means: &RankingMeans) -> Vec<String> {
+    let mut columns = vec![];
+    for (precision, recall) in means.precision_at.iter().zip(&means.recall_at) {
+        columns.extend([precision.to_string(), recall.to_string()]);
+    }
+    columns.extend([
+        means.mean_average_precision.to_string(),
+        means.ndcg.to_string(),
+        means
+            .lines_before_first_relevant
+            .map(|l| l.to_string())
+            .unwrap_or_default(),
+    ]);
+    columns
+}
+
+/// The confidence intervals of the micro-averaged then the macro-averaged scores of a type.
+fn intervals(evaluation: &Evaluation, args: &EvaluateArgs) -> Option<[Interval; 8]> {
+    evaluation.intervals(args.beta, args.resamples, args.confidence, args.resampling_seed)
+}
+
+fn print_comparisons(comparisons: &[Comparison], args: &EvaluateArgs, logs: usize) {
+    println!(
+        "paired tests of the {} of the {} logs with relevant lines, and McNemar's test of the decisions on their lines\n",
+        args.metric.name(), logs
+    );
+    println!(
+        "{:<20} {:<20} {:>10} {:>10} {:>10} {:>12} {:>10} {:>10} {:>10}",
+        "first",
+        "second",
+        "mean diff",
+        "wilcoxon",
+        "p-value",
+        "permutation",
+        "only 1st",
+        "only 2nd",
+        "mcnemar"
+    );
+    let p_value = |test: Option<Test>| test.map_or("-".to_string(), |t| format!("{:.4}", t.p_value));
+    for comparison in comparisons {
+        println!(
+            "{:<20} {:<20} {:>10} {:>10} {:>10} {:>12} {:>10} {:>10} {:>10}",
+            comparison.first.name(),
+            comparison.second.name(),
+            comparison
+                .permutation
+                .map_or("-".to_string(), |t| format!("{:+.3}", t.statistic)),
+            comparison
+                .wilcoxon
+                .map_or("-".to_string(), |t| format!("{:.1}", t.statistic)),
+            p_value(comparison.wilcoxon),
+            p_value(comparison.permutation),
+            comparison.discordant.map_or("-".to_string(), |d| d.0.to_string()),
+            comparison.discordant.map_or("-".to_string(), |d| d.1.to_string()),
+            p_value(comparison.mcnemar),
+        );
+    }
+}
+
+/// A row of the csv of the scores: a log, or the micro or macro average of the logs (with `micro`
+/// and `macro` as path).
+struct Row<'a> {
+    path: &'a str,
+    evaluation: &'a Evaluation,
+    /// The step of the row broken down by step, none for every line of the logs
+    step: Option<&'a str>,
+    confusion: Confusion,
+    scores: Scores,
+    /// The ranking and region columns, only without tolerance (the ranking is not micro-averaged)
+    ranking: Vec<String>,
+    regions: Vec<String>,
+    /// The confidence intervals of the scores (the logs have none)
+    intervals: &'a [Interval],
+}
+
+impl Row<'_> {
+    /// The columns of the row, `ranking_columns` being the number of ranking columns of the header,
+    /// with a step column if the scores are broken down by step.
+    fn record(self, ranking_columns: usize, by_step: bool) -> Vec<String> {
+        let mut record = vec![
+            self.path.to_string(),
+            self.evaluation.kind.clone(),
+            self.evaluation.tolerance.to_string(),
+        ];
+        if by_step {
+            record.push(self.step.unwrap_or_default().to_string());
+        }
+        record.extend([
+            self.confusion.true_positives.to_string(),
+            self.confusion.false_positives.to_string(),
+            self.confusion.false_negatives.to_string(),
+            self.confusion.true_negatives.to_string(),
+            self.scores.precision.to_string(),
+            self.scores.recall.to_string(),
+            self.scores.f1.to_string(),
+            self.scores.f_beta.to_string(),
+        ]);
+        let mut ranking = self.ranking;
+        ranking.resize(ranking_columns, String::new());
+        record.extend(ranking);
+        let mut regions = self.regions;
+        regions.resize(REGION_COLUMNS, String::new());
+        record.extend(regions);
+        record.extend(
+            self.intervals
+                .iter()
+                .flat_map(|interval| [interval.low.to_string(), interval.high.to_string()]),
+        );
+        record
+    }
+}
+
+/// The number of region columns of the csv.
+const REGION_COLUMNS: usize = 5;
+
+/// Print the counts and the scores of each log and type, then their micro and macro averages (with
+/// `micro` and `macro` as path). Broken down by step, the sums of each step of every log follow
+/// (with `micro` as path and the name of the step), without tolerance.
+fn print_csv(evaluations: &[Evaluation], args: &EvaluateArgs) -> Result<()> {
+    let beta = args.beta;
+    let mut output = csv::Writer::from_writer(std::io::stdout());
+    let scores = ["precision", "recall", "f1", &format!("f{}", beta)].map(str::to_string);
+    let mut header = ["path", "type", "tolerance"].map(str::to_string).to_vec();
+    if args.by_step {
+        header.push("step".to_string());
+    }
+    header.extend(["tp", "fp", "fn", "tn"].map(str::to_string));
+    header.extend(scores.iter().cloned());
+    for k in &args.cutoffs {
+        header.extend([format!("precision@{}", k), format!("recall@{}", k)]);
+    }
+    header.extend(["average_precision", "ndcg", "lines_before_first_relevant"].map(str::to_string));
+    let ranking_columns = 2 * args.cutoffs.len() + 3;
+    header.extend(["regions", "hits", "hit_rate", "coverage", "fragmentation"].map(str::to_string));
+    if args.resamples > 0 {
+        header.extend(
+            scores
+                .iter()
+                .flat_map(|score| [format!("{}_low", score), format!("{}_high", score)]),
+        );
+    }
+    output.write_record(&header)?;
+    let mut write = |row: Row| {
+        let mut record = row.record(ranking_columns, args.by_step);
+        // the logs have no interval
+        record.resize(header.len(), String::new());
+        output.write_record(&record)
+    };
+    for evaluation in evaluations {
+        for (((log, confusion), ranking), regions) in evaluation
+            .logs
+            .iter()
+            .zip(&evaluation.rankings)
+            .zip(&evaluation.regions)
+        {
+            let (ranking, regions) = if evaluation.tolerance == 0 {
+                (
+                    ranking_columns_of(&RankingMeans::of(
+                        std::slice::from_ref(ranking),
+                        args.cutoffs.len(),
+                    )),
+                    region_columns(regions),
+                )
+            } else {
+                (vec![], vec![])
+            };
+            write(Row {
+                path: log,
+                evaluation,
+                step: None,
+                confusion: *confusion,
+                scores: Scores::of(confusion, beta),
+                ranking,
+                regions,
+                intervals: &[],
+            })?;
+        }
+    }
+    for evaluation in evaluations {
+        let confusions = evaluation.confusions();
+        let total = confusions.iter().sum();
+        let intervals = intervals(evaluation, args);
+        let (micro, macro_average) = intervals.as_ref().map_or((&[][..], &[][..]), |i| i.split_at(4));
+        write(Row {
+            path: "micro",
+            evaluation,
+            step: None,
+            confusion: total,
+            scores: Scores::micro(&confusions, beta),
+            ranking: vec![],
+            regions: if evaluation.tolerance == 0 {
+                region_columns(&evaluation.regions.iter().sum())
+            } else {
+                vec![]
+            },
+            intervals: micro,
+        })?;
+        let (ranking, regions) = if evaluation.tolerance == 0 {
+            (
+                ranking_columns_of(&RankingMeans::of(&evaluation.rankings, args.cutoffs.len())),
+                mean_region_columns(&RegionMeans::of(&evaluation.regions)),
+            )
+        } else {
+            (vec![], vec![])
+        };
+        write(Row {
+            path: "macro",
+            evaluation,
+            step: None,
+            confusion: total,
+            scores: Scores::macro_average(&confusions, beta),
+            ranking,
+            regions,
+            intervals: macro_average,
+        })?;
+    }
+    for evaluation in evaluations.iter().filter(|e| e.tolerance == 0) {
+        for (step, confusion) in &evaluation.steps {
+            write(Row {
+                path: "micro",
+                evaluation,
+                step: Some(step),
+                confusion: *confusion,
+                scores: Scores::of(confusion, beta),
+                ranking: vec![],
+                regions: vec![],
+                intervals: &[],
+            })?;
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn print_comparisons_csv(comparisons: &[Comparison], args: &EvaluateArgs) -> Result<()> {
+    let mut output = csv::Writer::from_writer(std::io::stdout());
+    output.write_record([
+        "first",
+        "second",
+        "tolerance",
+        "metric",
+        "mean_difference",
+        "wilcoxon",
+        "wilcoxon_p",
+        "permutation_p",
+        "only_first",
+        "only_second",
+        "mcnemar",
+        "mcnemar_p",
+    ])?;
+    let value = |test: Option<Test>, value: fn(Test) -> f64| test.map(value).map(|v| v.to_string());
+    for comparison in comparisons {
+        output.write_record([
+            comparison.first.kind.clone(),
+            comparison.second.kind.clone(),
+            comparison.first.tolerance.to_string(),
+            args.metric.name(),
+            value(comparison.permutation, |t| t.statistic).unwrap_or_default(),
+            value(comparison.wilcoxon, |t| t.statistic).unwrap_or_default(),
+            value(comparison.wilcoxon, |t| t.p_value).unwrap_or_default(),
+            value(comparison.permutation, |t| t.p_value).unwrap_or_default(),
+            comparison.discordant.map(|d| d.0.to_string()).unwrap_or_default(),
+            comparison.discordant.map(|d| d.1.to_string()).unwrap_or_default(),
+            value(comparison.mcnemar, |t| t.statistic).unwrap_or_default(),
+            value(comparison.mcnemar, |t| t.p_value).unwrap_or_default(),
+        ])?;
+    }
+    output.flush()?;
+    Ok(())
+}
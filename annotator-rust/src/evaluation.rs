@@ -0,0 +1,858 @@
+//! The evaluation of the lines selected by an algorithm against a reference (the lines selected by
+//! the humans, or the gold set): each line of a log is relevant or not, and selected or not.
+//! The selections of every type are read from csv files (see [`load_selections`]) and evaluated on
+//! the failure logs of a dataset (see [`Evaluations`]).
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    iter::Sum,
+    ops::{Add, AddAssign, Range},
+    path::Path,
+};
+
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    statistics::{self, Interval, Test},
+    Dataset, LogParser, Result,
+};
+
+/// The confusion matrix of the lines of one or several logs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Confusion {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub true_negatives: usize,
+}
+
+impl Confusion {
+    /// The confusion matrix of a log of `lines` lines, the `reference` lines being the relevant
+    /// ones.
+    pub fn of(lines: usize, reference: &HashSet<usize>, selected: &HashSet<usize>) -> Self {
+        let true_positives = reference.intersection(selected).count();
+        let false_positives = selected.len() - true_positives;
+        let false_negatives = reference.len() - true_positives;
+        Self {
+            true_positives,
+            false_positives,
+            false_negatives,
+            // the selections can be out of the log if it changed since
+            true_negatives: lines.saturating_sub(true_positives + false_positives + false_negatives),
+        }
+    }
+
+    /// The confusion matrix of a log when a selected line counts as a true positive if it is at
+    /// most `tolerance` lines away from a relevant line, each relevant line being matched to one
+    /// selected line at most (see [`matching`]).
+    pub fn within(
+        lines: usize,
+        reference: &HashSet<usize>,
+        selected: &HashSet<usize>,
+        tolerance: usize,
+    ) -> Self {
+        let true_positives = matching(reference, selected, tolerance).len();
+        let false_positives = selected.len() - true_positives;
+        let false_negatives = reference.len() - true_positives;
+        Self {
+            true_positives,
+            false_positives,
+            false_negatives,
+            true_negatives: lines.saturating_sub(true_positives + false_positives + false_negatives),
+        }
+    }
+
+    /// The part of the selected lines that are relevant, 0 when nothing is selected.
+    pub fn precision(&self) -> f64 {
+        ratio(self.true_positives, self.true_positives + self.false_positives)
+    }
+
+    /// The part of the relevant lines that are selected, 0 when nothing is relevant.
+    pub fn recall(&self) -> f64 {
+        ratio(self.true_positives, self.true_positives + self.false_negatives)
+    }
+
+    pub fn f1(&self) -> f64 {
+        self.f_beta(1.0)
+    }
+
+    /// The weighted harmonic mean of the precision and the recall, the recall being `beta` times
+    /// as important as the precision. It is 0 when both are 0.
+    pub fn f_beta(&self, beta: f64) -> f64 {
+        f_beta(self.precision(), self.recall(), beta)
+    }
+}
+
+/// The largest one-to-one matching of the selected lines with the relevant lines at most
+/// `tolerance` lines away, as pairs of (relevant line, selected line) in the order of the relevant
+/// lines. With a tolerance of 0, it is the selected lines that are relevant.
+///
+/// The selected lines that are relevant are matched to themselves first. The matching is then
+/// grown by augmenting paths (Kuhn's algorithm) from each relevant line left: a relevant line only
+/// leaves its own line for another one of its window when it lets one more line be matched.
+pub fn matching(
+    reference: &HashSet<usize>,
+    selected: &HashSet<usize>,
+    tolerance: usize,
+) -> Vec<(usize, usize)> {
+    let mut relevant = reference.iter().copied().collect::<Vec<_>>();
+    let mut lines = selected.iter().copied().collect::<Vec<_>>();
+    relevant.sort_unstable();
+    lines.sort_unstable();
+    // the selected lines in the window of each relevant line, as a range of `lines`
+    let windows = relevant
+        .iter()
+        .map(|r| {
+            lines.partition_point(|s| s + tolerance < *r)
+                ..lines.partition_point(|s| *s <= r.saturating_add(tolerance))
+        })
+        .collect::<Vec<_>>();
+    // the relevant line matched to each selected line
+    let mut matched = lines
+        .iter()
+        .map(|s| relevant.binary_search(s).ok())
+        .collect::<Vec<_>>();
+    // the last relevant line from which each selected line was visited
+    let mut visited = vec![None; lines.len()];
+    for start in (0..relevant.len()).filter(|r| !selected.contains(&relevant[*r])) {
+        // the relevant lines of the path, with the next selected line of their window to try
+        let mut path = vec![(start, windows[start].start)];
+        while let Some(&(r, next)) = path.last() {
+            if next == windows[r].end {
+                path.pop();
+                continue;
+            }
+            let last = path.len() - 1;
+            path[last].1 += 1;
+            if visited[next] == Some(start) {
+                continue;
+            }
+            visited[next] = Some(start);
+            match matched[next] {
+                Some(other) => path.push((other, windows[other].start)),
+                None => {
+                    // each relevant line of the path takes the selected line it tried last
+                    for (r, next) in path {
+                        matched[next - 1] = Some(r);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    let mut pairs = matched
+        .iter()
+        .zip(&lines)
+        .filter_map(|(r, s)| r.map(|r| (relevant[r], *s)))
+        .collect::<Vec<_>>();
+    pairs.sort_unstable();
+    pairs
+}
+
+/// The selected lines that are not relevant but at most `tolerance` lines away from a relevant
+/// line they are matched to (see [`matching`]).
+pub fn near_misses(
+    reference: &HashSet<usize>,
+    selected: &HashSet<usize>,
+    tolerance: usize,
+) -> HashSet<usize> {
+    matching(reference, selected, tolerance)
+        .into_iter()
+        .map(|(_, s)| s)
+        .filter(|s| !reference.contains(s))
+        .collect()
+}
+
+/// The scores of the order in which the selected lines of a log are read (e.g. by decreasing
+/// score), which is what a developer scanning them experiences.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankingScores {
+    /// The precision of the first `k` lines, for each cutoff `k`
+    pub precision_at: Vec<f64>,
+    /// The recall of the first `k` lines, for each cutoff `k`
+    pub recall_at: Vec<f64>,
+    /// The mean of the precision at the rank of each relevant line (0 for the ones not selected)
+    pub average_precision: f64,
+    /// The discounted cumulative gain, divided by the one of the best ranking
+    pub ndcg: f64,
+    /// The number of lines read before the first relevant one, `None` if none is selected
+    pub lines_before_first_relevant: Option<usize>,
+}
+
+impl RankingScores {
+    /// The scores of the ranked lines of a log (the first line is read first), the `reference`
+    /// lines being the relevant ones.
+    pub fn of(reference: &HashSet<usize>, ranking: &[usize], cutoffs: &[usize]) -> Self {
+        let relevant = ranking.iter().map(|l| reference.contains(l)).collect::<Vec<_>>();
+        let found_at = |k: usize| relevant.iter().take(k).filter(|r| **r).count();
+        let mut average_precision = 0.0;
+        let mut dcg = 0.0;
+        for (found, (rank, _)) in relevant.iter().enumerate().filter(|(_, r)| **r).enumerate() {
+            average_precision += (found + 1) as f64 / (rank + 1) as f64;
+            dcg += discount(rank);
+        }
+        let ideal = (0..reference.len()).map(discount).sum::<f64>();
+        Self {
+            precision_at: cutoffs.iter().map(|k| ratio(found_at(*k), *k)).collect(),
+            recall_at: cutoffs
+                .iter()
+                .map(|k| ratio(found_at(*k), reference.len()))
+                .collect(),
+            average_precision: if reference.is_empty() {
+                0.0
+            } else {
+                average_precision / reference.len() as f64
+            },
+            ndcg: if ideal == 0.0 { 0.0 } else { dcg / ideal },
+            lines_before_first_relevant: relevant.iter().position(|r| *r),
+        }
+    }
+}
+
+/// The discount of the gain of a line at a rank (from 0).
+fn discount(rank: usize) -> f64 {
+    1.0 / (rank as f64 + 2.0).log2()
+}
+
+/// The consecutive lines of a selection (e.g. a stack trace, or a compiler error with its context),
+/// in the order of the lines.
+pub fn regions(lines: &HashSet<usize>) -> Vec<Range<usize>> {
+    let mut lines = lines.iter().copied().collect::<Vec<_>>();
+    lines.sort_unstable();
+    let mut regions: Vec<Range<usize>> = vec![];
+    for line in lines {
+        match regions.last_mut() {
+            Some(region) if region.end == line => region.end += 1,
+            _ => regions.push(line..line + 1),
+        }
+    }
+    regions
+}
+
+/// How the regions of the reference (the consecutive relevant lines) are found by a selection, in
+/// one or several logs.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RegionCounts {
+    /// The number of regions of the reference
+    pub regions: usize,
+    /// The number of regions with at least one selected line
+    pub hits: usize,
+    /// The sum of the part of each region that is selected
+    pub covered: f64,
+    /// The sum of the number of regions of the selection overlapping each region that is hit
+    pub fragments: usize,
+}
+
+impl RegionCounts {
+    pub fn of(reference: &HashSet<usize>, selected: &HashSet<usize>) -> Self {
+        let selected_regions = regions(selected);
+        let mut counts = Self::default();
+        for region in regions(reference) {
+            counts.regions += 1;
+            let found = region.clone().filter(|l| selected.contains(l)).count();
+            if found == 0 {
+                continue;
+            }
+            counts.hits += 1;
+            counts.covered += found as f64 / region.len() as f64;
+            counts.fragments += selected_regions
+                .iter()
+                .filter(|s| s.start < region.end && region.start < s.end)
+                .count();
+        }
+        counts
+    }
+
+    /// The part of the regions with at least one selected line, 0 without region.
+    pub fn hit_rate(&self) -> f64 {
+        ratio(self.hits, self.regions)
+    }
+
+    /// The mean part of each region that is selected, 0 without region.
+    pub fn coverage(&self) -> f64 {
+        if self.regions == 0 {
+            0.0
+        } else {
+            self.covered / self.regions as f64
+        }
+    }
+
+    /// The mean number of regions of the selection overlapping each region that is hit (1 when
+    /// each region is found in one piece), `None` when no region is hit.
+    pub fn fragmentation(&self) -> Option<f64> {
+        (self.hits > 0).then(|| self.fragments as f64 / self.hits as f64)
+    }
+}
+
+impl Add for RegionCounts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            regions: self.regions + other.regions,
+            hits: self.hits + other.hits,
+            covered: self.covered + other.covered,
+            fragments: self.fragments + other.fragments,
+        }
+    }
+}
+
+impl<'a> Sum<&'a RegionCounts> for RegionCounts {
+    fn sum<I: Iterator<Item = &'a RegionCounts>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, c| total + *c)
+    }
+}
+
+/// The number of lines that only the first selection got right and that only the second one got
+/// right (among the lines selected by one of them only), to compare their decisions line by line.
+pub fn discordant(
+    reference: &HashSet<usize>,
+    first: &HashSet<usize>,
+    second: &HashSet<usize>,
+) -> (usize, usize) {
+    let only_in_first = first.difference(second).collect::<Vec<_>>();
+    let only_in_second = second.difference(first).collect::<Vec<_>>();
+    let relevant = |lines: &[&usize]| lines.iter().filter(|l| reference.contains(l)).count();
+    (
+        relevant(&only_in_first) + only_in_second.len() - relevant(&only_in_second),
+        relevant(&only_in_second) + only_in_first.len() - relevant(&only_in_first),
+    )
+}
+
+impl Add for Confusion {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            true_positives: self.true_positives + other.true_positives,
+            false_positives: self.false_positives + other.false_positives,
+            false_negatives: self.false_negatives + other.false_negatives,
+            true_negatives: self.true_negatives + other.true_negatives,
+        }
+    }
+}
+
+impl AddAssign for Confusion {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<'a> Sum<&'a Confusion> for Confusion {
+    fn sum<I: Iterator<Item = &'a Confusion>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, c| total + *c)
+    }
+}
+
+/// The scores of an algorithm on one or several logs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scores {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    pub f_beta: f64,
+}
+
+impl Scores {
+    pub fn of(confusion: &Confusion, beta: f64) -> Self {
+        Self {
+            precision: confusion.precision(),
+            recall: confusion.recall(),
+            f1: confusion.f1(),
+            f_beta: confusion.f_beta(beta),
+        }
+    }
+
+    /// The scores of the lines of every log together: each line counts as much.
+    pub fn micro(confusions: &[Confusion], beta: f64) -> Self {
+        Self::of(&confusions.iter().sum(), beta)
+    }
+
+    /// The mean of the scores of each log: each log counts as much, whatever its length.
+    pub fn macro_average(confusions: &[Confusion], beta: f64) -> Self {
+        let n = confusions.len().max(1) as f64;
+        let mean = |score: &dyn Fn(&Confusion) -> f64| confusions.iter().map(score).sum::<f64>() / n;
+        Self {
+            precision: mean(&Confusion::precision),
+            recall: mean(&Confusion::recall),
+            f1: mean(&Confusion::f1),
+            f_beta: mean(&|c| c.f_beta(beta)),
+        }
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+fn f_beta(precision: f64, recall: f64, beta: f64) -> f64 {
+    let weight = beta * beta;
+    let denominator = weight * precision + recall;
+    if denominator == 0.0 {
+        0.0
+    } else {
+        (1.0 + weight) * precision * recall / denominator
+    }
+}
+
+/// The step of the lines outside the steps of a log (e.g. before the first one).
+pub const NO_STEP: &str = "(no step)";
+
+/// The score of each log compared by the paired tests.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Metric {
+    Precision,
+    Recall,
+    F1,
+    FBeta,
+}
+
+impl Metric {
+    pub fn name(self) -> String {
+        self.to_possible_value()
+            .map_or(String::new(), |v| v.get_name().to_string())
+    }
+
+    pub fn of(self, confusion: &Confusion, beta: f64) -> f64 {
+        match self {
+            Metric::Precision => confusion.precision(),
+            Metric::Recall => confusion.recall(),
+            Metric::F1 => confusion.f1(),
+            Metric::FBeta => confusion.f_beta(beta),
+        }
+    }
+}
+
+/// The columns of a csv file of selections, found by their name in its header: the path, the type
+/// and the line, and the optional label of the human selections and score or rank of the
+/// algorithms (the files do not all have the same columns, e.g. when merged).
+#[derive(Debug, Clone, Copy)]
+pub struct SelectionColumns {
+    path: usize,
+    kind: usize,
+    line: usize,
+    label: Option<usize>,
+    pub score: Option<usize>,
+    pub rank: Option<usize>,
+}
+
+impl SelectionColumns {
+    pub fn new(headers: &csv::StringRecord) -> Result<Self> {
+        let position = |name| headers.iter().position(|h| h == name);
+        match (position("path"), position("type"), position("line")) {
+            (Some(path), Some(kind), Some(line)) => Ok(SelectionColumns {
+                path,
+                kind,
+                line,
+                label: position("label"),
+                score: position("score"),
+                rank: position("rank"),
+            }),
+            _ => bail!("{:?} is not a header of selections (path,type,line)", headers),
+        }
+    }
+
+    /// The path, the type, the line and the label of a selection.
+    pub fn parse(&self, record: &csv::StringRecord) -> Result<(String, String, usize, Option<String>)> {
+        match (
+            record.get(self.path),
+            record.get(self.kind),
+            record.get(self.line).map(str::parse),
+        ) {
+            (Some(path), Some(kind), Some(Ok(line))) => Ok((
+                path.to_string(),
+                kind.to_string(),
+                line,
+                self.label
+                    .and_then(|l| record.get(l))
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_string),
+            )),
+            _ => bail!("{:?} is not a selection", record),
+        }
+    }
+}
+
+/// The lines selected in each log, by type.
+pub type Selections = BTreeMap<String, BTreeMap<String, Selected>>;
+
+/// The lines selected by a type in a log.
+#[derive(Debug, Default)]
+pub struct Selected {
+    pub lines: HashSet<usize>,
+    /// The position of the lines with a score or a rank in the ranking: the lowest are read first
+    pub positions: HashMap<usize, f64>,
+}
+
+impl Selected {
+    /// The lines in the order they are read: by decreasing score or increasing rank, then the lines
+    /// without score in the order of the log (the order of the types that give a set of lines).
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ranking = self.lines.iter().copied().collect::<Vec<_>>();
+        ranking.sort_by(|a, b| {
+            match (self.positions.get(a), self.positions.get(b)) {
+                (Some(x), Some(y)) => x.total_cmp(y),
+                (x, y) => x.is_none().cmp(&y.is_none()),
+            }
+            .then(a.cmp(b))
+        });
+        ranking
+    }
+}
+
+/// Load the selections of the csv files. The annotators of the reference are merged (e.g. every
+/// `human:<id>` in `human`), and with a label, only the lines of the reference with this label are
+/// kept.
+/// The `score` (the highest first) or `rank` (the lowest first) column of a file gives the order of
+/// the lines of each log; a line selected several times keeps its best position.
+pub fn load_selections<P: AsRef<Path>>(
+    files: &[P],
+    reference: &str,
+    label: Option<&str>,
+) -> Result<Selections> {
+    let mut selections = Selections::new();
+    for file in files {
+        let file = file.as_ref().display();
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(file.to_string())
+            .with_context(|| format!("could not read {}", file))?;
+        let columns = reader
+            .headers()
+            .map_err(anyhow::Error::from)
+            .and_then(SelectionColumns::new)
+            .with_context(|| format!("could not read the header of {}", file))?;
+        for (index, record) in reader.records().enumerate() {
+            let record =
+                record.with_context(|| format!("could not read the record {} of {}", index + 1, file))?;
+            // the scores are turned into positions: the highest score is read first
+            let position = match (
+                columns.score.and_then(|s| record.get(s)),
+                columns.rank.and_then(|r| record.get(r)),
+            ) {
+                (Some(score), _) if !score.is_empty() => Some(-score.parse::<f64>().with_context(|| {
+                    format!(
+                        "the score of the record {} of {} is not a number",
+                        index + 1,
+                        file
+                    )
+                })?),
+                (_, Some(rank)) if !rank.is_empty() => Some(rank.parse::<f64>().with_context(|| {
+                    format!("the rank of the record {} of {} is not a number", index + 1, file)
+                })?),
+                _ => None,
+            }
+            .filter(|p| !p.is_nan());
+            let (path, kind, line, selection_label) = columns
+                .parse(&record)
+                .with_context(|| format!("could not read the record {} of {}", index + 1, file))?;
+            let in_reference = kind
+                .strip_prefix(reference)
+                .is_some_and(|annotator| annotator.is_empty() || annotator.starts_with(':'));
+            let kind = if in_reference { reference.to_string() } else { kind };
+            if kind == reference && label.is_some() && selection_label.as_deref() != label {
+                continue;
+            }
+            let selected = selections.entry(path).or_default().entry(kind).or_default();
+            selected.lines.insert(line);
+            if let Some(position) = position {
+                let best = selected.positions.entry(line).or_insert(position);
+                *best = best.min(position);
+            }
+        }
+    }
+    Ok(selections)
+}
+
+/// How the selections are evaluated.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The type of the reference, in which its annotators are merged
+    pub reference: String,
+    /// The tolerance of the second evaluation of each type (0 for none)
+    pub tolerance: usize,
+    /// The numbers of lines read for the precision and the recall of the first lines of the ranking
+    pub cutoffs: Vec<usize>,
+    /// If the counts are also broken down by step of the jobs
+    pub by_step: bool,
+}
+
+/// The confusion matrices of a type with a tolerance, for each log, with the lines it selected in
+/// each log, the scores of their ranking and how they find the regions of the reference.
+#[derive(Debug)]
+pub struct Evaluation {
+    pub kind: String,
+    pub tolerance: usize,
+    pub logs: Vec<(String, Confusion)>,
+    pub selected: Vec<HashSet<usize>>,
+    pub rankings: Vec<RankingScores>,
+    pub regions: Vec<RegionCounts>,
+    /// The confusion matrices of the lines of each step of every log, by name of the step
+    pub steps: BTreeMap<String, Confusion>,
+}
+
+impl Evaluation {
+    fn new(kind: &str, tolerance: usize) -> Self {
+        Self {
+            kind: kind.to_string(),
+            tolerance,
+            logs: vec![],
+            selected: vec![],
+            rankings: vec![],
+            regions: vec![],
+            steps: BTreeMap::new(),
+        }
+    }
+
+    /// The type, with its tolerance if it has one.
+    pub fn name(&self) -> String {
+        if self.tolerance == 0 {
+            self.kind.clone()
+        } else {
+            format!("{} ±{}", self.kind, self.tolerance)
+        }
+    }
+
+    pub fn confusions(&self) -> Vec<Confusion> {
+        self.logs.iter().map(|(_, c)| *c).collect()
+    }
+
+    /// The confidence intervals of the micro-averaged then the macro-averaged scores, from
+    /// `resamples` resamples of the logs.
+    pub fn intervals(
+        &self,
+        beta: f64,
+        resamples: usize,
+        confidence: f64,
+        seed: u64,
+    ) -> Option<[Interval; 8]> {
+        // the same resamples for every type
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        statistics::bootstrap(
+            &self.confusions(),
+            resamples,
+            confidence,
+            &mut rng,
+            |confusions| {
+                let micro = Scores::micro(confusions, beta);
+                let macro_average = Scores::macro_average(confusions, beta);
+                [
+                    micro.precision,
+                    micro.recall,
+                    micro.f1,
+                    micro.f_beta,
+                    macro_average.precision,
+                    macro_average.recall,
+                    macro_average.f1,
+                    macro_average.f_beta,
+                ]
+            },
+        )
+    }
+}
+
+/// The evaluations of every type other than the reference, without tolerance then with it, on the
+/// logs with relevant lines (the csv files only have the selected lines), with the relevant lines
+/// of each log.
+#[derive(Debug)]
+pub struct Evaluations {
+    pub evaluations: Vec<Evaluation>,
+    pub references: Vec<HashSet<usize>>,
+}
+
+impl Evaluations {
+    /// Evaluate the selections of the failure logs of the dataset.
+    pub fn of(
+        selections: &Selections,
+        settings: &Settings,
+        dataset: &Dataset,
+        parser: &LogParser,
+    ) -> Result<Self> {
+        let kinds = selections
+            .values()
+            .flat_map(|types| types.keys())
+            .filter(|kind| **kind != settings.reference)
+            .collect::<BTreeSet<_>>();
+        let tolerances = if settings.tolerance == 0 {
+            vec![0]
+        } else {
+            vec![0, settings.tolerance]
+        };
+        let mut evaluations = tolerances
+            .iter()
+            .flat_map(|tolerance| kinds.iter().map(|kind| Evaluation::new(kind, *tolerance)))
+            .collect::<Vec<_>>();
+        let mut references = vec![];
+        let none = Selected::default();
+        for (log, types) in selections {
+            let Some(reference) = types.get(&settings.reference) else {
+                continue;
+            };
+            let failure_log = dataset.failure_log(log);
+            let log_file = parser
+                .parse_log_file(&failure_log)
+                .with_context(|| format!("could not read {}", failure_log.display()))?;
+            let lines = log_file.lines.len();
+            let mut steps = BTreeMap::<&str, HashSet<usize>>::new();
+            if settings.by_step {
+                for line in 0..lines {
+                    let step = log_file
+                        .step_of(line)
+                        .map_or(NO_STEP, |s| &log_file.sections[s].name[..]);
+                    steps.entry(step).or_default().insert(line);
+                }
+            }
+            let reference = &reference.lines;
+            for evaluation in &mut evaluations {
+                let selected = types.get(&evaluation.kind).unwrap_or(&none);
+                let confusion = Confusion::within(lines, reference, &selected.lines, evaluation.tolerance);
+                evaluation.logs.push((log.clone(), confusion));
+                evaluation.selected.push(selected.lines.clone());
+                evaluation.rankings.push(RankingScores::of(
+                    reference,
+                    &selected.ranking(),
+                    &settings.cutoffs,
+                ));
+                evaluation
+                    .regions
+                    .push(RegionCounts::of(reference, &selected.lines));
+                if evaluation.tolerance == 0 {
+                    for (step, step_lines) in &steps {
+                        let confusion = Confusion::of(
+                            step_lines.len(),
+                            &reference.intersection(step_lines).copied().collect(),
+                            &selected.lines.intersection(step_lines).copied().collect(),
+                        );
+                        *evaluation.steps.entry(step.to_string()).or_default() += confusion;
+                    }
+                }
+            }
+            references.push(reference.clone());
+        }
+        Ok(Self {
+            evaluations,
+            references,
+        })
+    }
+
+    /// Test the differences between each pair of types with the same tolerance: on the `metric` of
+    /// each log (Wilcoxon signed-rank and paired permutation tests), and on the decisions on each
+    /// line without tolerance (McNemar's test).
+    pub fn compare(&self, metric: Metric, beta: f64, permutations: usize, seed: u64) -> Vec<Comparison<'_>> {
+        let mut comparisons = vec![];
+        for (i, first) in self.evaluations.iter().enumerate() {
+            for second in self.evaluations[i + 1..]
+                .iter()
+                .filter(|e| e.tolerance == first.tolerance)
+            {
+                let differences = first
+                    .logs
+                    .iter()
+                    .zip(&second.logs)
+                    .map(|((_, a), (_, b))| metric.of(a, beta) - metric.of(b, beta))
+                    .collect::<Vec<_>>();
+                let discordant = (first.tolerance == 0).then(|| {
+                    self.references
+                        .iter()
+                        .zip(first.selected.iter().zip(&second.selected))
+                        .map(|(reference, (a, b))| discordant(reference, a, b))
+                        .fold((0, 0), |(x, y), (a, b)| (x + a, y + b))
+                });
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                comparisons.push(Comparison {
+                    first,
+                    second,
+                    wilcoxon: statistics::wilcoxon_signed_rank(&differences),
+                    permutation: statistics::paired_permutation(&differences, permutations, &mut rng),
+                    discordant,
+                    mcnemar: discordant.and_then(|(a, b)| statistics::mcnemar(a, b)),
+                });
+            }
+        }
+        comparisons
+    }
+}
+
+/// The tests of the difference between two types.
+#[derive(Debug)]
+pub struct Comparison<'a> {
+    pub first: &'a Evaluation,
+    pub second: &'a Evaluation,
+    /// The tests on the paired scores of each log
+    pub wilcoxon: Option<Test>,
+    pub permutation: Option<Test>,
+    /// The number of lines that only the first type and only the second type got right, without
+    /// tolerance
+    pub discordant: Option<(usize, usize)>,
+    pub mcnemar: Option<Test>,
+}
+
+/// The means of the ranking scores of the logs. The lines read before the first relevant line are
+/// averaged over the logs in which a relevant line is selected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankingMeans {
+    pub precision_at: Vec<f64>,
+    pub recall_at: Vec<f64>,
+    pub mean_average_precision: f64,
+    pub ndcg: f64,
+    pub lines_before_first_relevant: Option<f64>,
+    /// The number of logs in which a relevant line is selected
+    pub found: usize,
+}
+
+impl RankingMeans {
+    pub fn of(rankings: &[RankingScores], cutoffs: usize) -> Self {
+        let n = rankings.len().max(1) as f64;
+        let mean = |score: &dyn Fn(&RankingScores) -> f64| rankings.iter().map(score).sum::<f64>() / n;
+        let firsts = rankings
+            .iter()
+            .filter_map(|r| r.lines_before_first_relevant)
+            .collect::<Vec<_>>();
+        Self {
+            precision_at: (0..cutoffs).map(|i| mean(&|r| r.precision_at[i])).collect(),
+            recall_at: (0..cutoffs).map(|i| mean(&|r| r.recall_at[i])).collect(),
+            mean_average_precision: mean(&|r| r.average_precision),
+            ndcg: mean(&|r| r.ndcg),
+            lines_before_first_relevant: (!firsts.is_empty())
+                .then(|| firsts.iter().sum::<usize>() as f64 / firsts.len() as f64),
+            found: firsts.len(),
+        }
+    }
+}
+
+/// The means of the region scores of the logs, with the total of their regions and hits. The
+/// fragmentation is averaged over the logs with a region hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionMeans {
+    pub regions: usize,
+    pub hits: usize,
+    pub hit_rate: f64,
+    pub coverage: f64,
+    pub fragmentation: Option<f64>,
+}
+
+impl RegionMeans {
+    pub fn of(regions: &[RegionCounts]) -> Self {
+        let total = regions.iter().sum::<RegionCounts>();
+        let n = regions.len().max(1) as f64;
+        let fragmentations = regions
+            .iter()
+            .filter_map(RegionCounts::fragmentation)
+            .collect::<Vec<_>>();
+        Self {
+            regions: total.regions,
+            hits: total.hits,
+            hit_rate: regions.iter().map(RegionCounts::hit_rate).sum::<f64>() / n,
+            coverage: regions.iter().map(RegionCounts::coverage).sum::<f64>() / n,
+            fragmentation: (!fragmentations.is_empty())
+                .then(|| fragmentations.iter().sum::<f64>() / fragmentations.len() as f64),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Context;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    atomic,
+    dataset::{Dataset, DatasetSpec, Diagnostic},
+    Result,
+};
+
+/// The name of the index file, written at the root of the dataset.
+pub const INDEX_FILE: &str = ".cidiff-index.json";
+
+/// Incremented when the format of the index changes, to rebuild the old ones.
+const INDEX_VERSION: u32 = 1;
+
+/// What is known about a log file without reading it again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogStats {
+    pub lines: usize,
+    pub bytes: u64,
+    /// The modification time, in nanoseconds since the epoch
+    pub mtime: u64,
+}
+
+/// A log pair of the dataset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// The path of the pair, relative to the dataset
+    pub path: PathBuf,
+    /// The language of the repository, when given by [`DatasetSpec::languages`]
+    pub language: Option<String>,
+    pub success: LogStats,
+    pub failure: LogStats,
+}
+
+impl IndexEntry {
+    /// The repository of the pair (`<owner>/<name>`), i.e. the parent directory of the run.
+    pub fn repository(&self) -> String {
+        repository_of(&self.path)
+    }
+}
+
+/// A directory of the dataset listed during the last walk, see [`crate::dataset::Walk`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct IndexedDirectory {
+    path: PathBuf,
+    mtime: u64,
+}
+
+/// Every log pair of a dataset, saved in [`INDEX_FILE`] so that the dataset is not walked and its
+/// logs are not counted again on each launch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetIndex {
+    version: u32,
+    /// The spec used to find the pairs
+    spec: DatasetSpec,
+    directories: Vec<IndexedDirectory>,
+    /// The pairs, sorted by path. The line limits of the spec are not applied here, see
+    /// [`DatasetIndex::accepted`].
+    pub entries: Vec<IndexEntry>,
+}
+
+impl DatasetIndex {
+    /// Load the index of the dataset, and bring it up to date: the dataset is walked again only if
+    /// one of its directories changed (or if the spec finds other pairs), and only the logs whose
+    /// size or modification time changed are counted again, in parallel. The index is saved when
+    /// it changed. The pairs that can not be read are returned as diagnostics.
+    pub fn open(dataset: &Dataset) -> Result<(Self, Vec<Diagnostic>)> {
+        let index_path = dataset.path.join(INDEX_FILE);
+        let mut diagnostics = vec![];
+        let previous = match fs::read_to_string(&index_path) {
+            Ok(content) => match serde_json::from_str::<DatasetIndex>(&content) {
+                Ok(index) if index.version == INDEX_VERSION => Some(index),
+                Ok(_) => None,
+                Err(e) => {
+                    diagnostics.push(Diagnostic {
+                        path: index_path.clone(),
+                        message: format!("rebuilt, the index was invalid ({})", e),
+                    });
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+        let up_to_date = previous.as_ref().is_some_and(|index| {
+            index.spec.same_pairs(&dataset.spec)
+                && index
+                    .directories
+                    .par_iter()
+                    .all(|d| mtime(&dataset.path.join(&d.path)).ok() == Some(d.mtime))
+        });
+        let (pairs, directories) = match &previous {
+            Some(index) if up_to_date => (
+                index.entries.iter().map(|e| e.path.clone()).collect(),
+                index.directories.clone(),
+            ),
+            _ => {
+                let mut pairs = vec![];
+                let walk = dataset.visit(&mut |path| pairs.push(path.to_path_buf()))?;
+                diagnostics.extend(walk.diagnostics);
+                let directories = walk
+                    .directories
+                    .into_par_iter()
+                    .filter_map(|path| {
+                        let mtime = mtime(&dataset.path.join(&path)).ok()?;
+                        Some(IndexedDirectory { path, mtime })
+                    })
+                    .collect();
+                (pairs, directories)
+            }
+        };
+
+        let known = previous
+            .as_ref()
+            .map(|index| {
+                index
+                    .entries
+                    .iter()
+                    .map(|e| (&e.path, e))
+                    .collect::<HashMap<_, _>>()
+            })
+            .unwrap_or_default();
+        let languages = match &dataset.spec.languages {
+            Some(path) => read_languages(path)?,
+            None => HashMap::new(),
+        };
+        let results = pairs
+            .into_par_iter()
+            .map(|path| {
+                let known = known.get(&path);
+                let success = log_stats(&dataset.success_log(&path), known.map(|e| &e.success))?;
+                let failure = log_stats(&dataset.failure_log(&path), known.map(|e| &e.failure))?;
+                Ok(IndexEntry {
+                    language: languages.get(&repository_of(&path)).cloned(),
+                    path,
+                    success,
+                    failure,
+                })
+            })
+            .collect::<Vec<Result<IndexEntry, Diagnostic>>>();
+        let mut entries = vec![];
+        for result in results {
+            match result {
+                Ok(entry) => entries.push(entry),
+                Err(diagnostic) => diagnostics.push(diagnostic),
+            }
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let index = Self {
+            version: INDEX_VERSION,
+            spec: dataset.spec.clone(),
+            directories,
+            entries,
+        };
+        if previous.as_ref() != Some(&index) {
+            // the dataset may be read only, the index is then rebuilt on each launch
+            let saved = serde_json::to_string(&index)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| atomic::write(&index_path, content));
+            if let Err(e) = saved {
+                diagnostics.push(Diagnostic {
+                    path: index_path,
+                    message: format!("the index could not be saved ({:#})", e),
+                });
+            }
+        }
+        Ok((index, diagnostics))
+    }
+
+    /// The pair at the given path, if it is in the dataset.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&IndexEntry> {
+        self.entries
+            .binary_search_by(|e| e.path.as_path().cmp(path.as_ref()))
+            .ok()
+            .map(|i| &self.entries[i])
+    }
+
+    /// The pairs whose logs are in the line limits of the spec.
+    pub fn accepted<'a>(&'a self, spec: &'a DatasetSpec) -> impl Iterator<Item = &'a IndexEntry> {
+        self.entries.iter().filter(|e| spec.accepts(e))
+    }
+
+    /// Split the given paths between the ones in the index and the ones that are not in the dataset
+    /// (anymore).
+    pub fn partition_known(&self, paths: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        paths.into_iter().partition(|p| self.get(p).is_some())
+    }
+}
+
+/// The stats of a log, reusing the known ones (and not counting its lines again) when the file did
+/// not change.
+fn log_stats(path: &Path, known: Option<&LogStats>) -> Result<LogStats, Diagnostic> {
+    let diagnostic = |e: io::Error| Diagnostic {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    };
+    let metadata = fs::metadata(path).map_err(diagnostic)?;
+    let bytes = metadata.len();
+    let mtime = modified(&metadata).map_err(diagnostic)?;
+    if let Some(known) = known.filter(|k| k.bytes == bytes && k.mtime == mtime) {
+        return Ok(known.clone());
+    }
+    let lines = fs::File::open(path)
+        .and_then(linecount::count_lines)
+        .map_err(diagnostic)?;
+    Ok(LogStats { lines, bytes, mtime })
+}
+
+fn modified(metadata: &fs::Metadata) -> io::Result<u64> {
+    let modified = metadata.modified()?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0))
+}
+
+fn mtime(path: &Path) -> io::Result<u64> {
+    modified(&fs::metadata(path)?)
+}
+
+fn repository_of(path: &Path) -> String {
+    path.parent()
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default()
+}
+
+/// Read the language of each repository from a csv file with a `repository` and a `language`
+/// column.
+fn read_languages(path: &Path) -> Result<HashMap<String, String>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("could not read the languages from {}", path.display()))?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .with_context(|| format!("{}: no {} column", path.display(), name))
+    };
+    let (repository, language) = (column("repository")?, column("language")?);
+    let mut languages = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        if let (Some(r), Some(l)) = (record.get(repository), record.get(language)) {
+            languages.insert(r.to_string(), l.to_string());
+        }
+    }
+    Ok(languages)
+}
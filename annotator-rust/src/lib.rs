@@ -0,0 +1,28 @@
+//! The code shared by the annotation tools (the human annotation TUI and the gpt/keyword
+//! annotators), so that every annotation refers to the same lines.
+
+pub mod agreement;
+pub mod ansi;
+pub mod atomic;
+pub mod dataset;
+pub mod diff;
+pub mod evaluation;
+pub mod index;
+pub mod manifest;
+pub mod parser;
+pub mod preprocess;
+pub mod sampling;
+pub mod statistics;
+pub mod steps;
+
+pub use dataset::{Dataset, DatasetSpec};
+pub use index::DatasetIndex;
+pub use manifest::Manifest;
+pub use parser::{LogLine, LogParser};
+pub use preprocess::{LogFormat, Preprocessor};
+pub use sampling::{Sample, Sampling};
+pub use steps::{Command, Log, Section};
+
+/// The result of the operations that can fail. The errors are only reported to the user, so they
+/// are `anyhow` errors with the context of what failed.
+pub type Result<T, E = anyhow::Error> = std::result::Result<T, E>;
@@ -1,12 +1,31 @@
-use std::{collections::HashMap, fs::{self, DirEntry, File}, io::{self, BufWriter, Write}, path::{Path, PathBuf}};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use clap::{Parser, Subcommand};
+use anyhow::{bail, Context};
+use chrono::{DateTime, Local};
+
+use cidiff_annotations::{
+    agreement::{self, Agreement},
+    atomic,
+    dataset::{self, DatasetArgs},
+    manifest::{self, MANIFEST_FILE},
+    Dataset, DatasetIndex, LogFormat, LogParser, Manifest, Result, Sampling,
+};
+use clap::{Args, Parser, Subcommand};
 use indicatif::ProgressStyle;
-use rand::{Rng, SeedableRng};
-use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 mod apollo;
 mod arthemis;
+mod clipboard;
+mod error;
+mod evaluate;
+mod search;
+mod side_by_side;
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
@@ -15,148 +34,541 @@ struct AppArgs {
     command: Commands,
     // The path to the dataset
     dataset: String,
-    /// Path to a file containing a list of paths from the dataset to use (instead of randomly
-    /// selecting the paths)
+    /// Path to a manifest (`sample.toml`) or to a file containing a list of paths from the dataset
+    /// to use (instead of randomly selecting the paths)
     #[arg(short, long)]
-    paths: Option<String>
+    paths: Option<String>,
+    #[command(flatten)]
+    sampling: Sampling,
+    /// The format of the logs. By default, it is detected from the first lines of each log
+    #[arg(long, value_enum, default_value_t = LogFormat::Auto)]
+    format: LogFormat,
+    #[command(flatten)]
+    dataset_args: DatasetArgs,
 }
 
 #[derive(Debug, Subcommand)]
 enum Commands {
     Annotate(apollo::ApolloArgs),
     Visu(arthemis::ArthemisArgs),
+    Agreement(AgreementArgs),
+    Evaluate(evaluate::EvaluateArgs),
+}
+
+/// Compute the agreement between the annotators, per log and overall
+#[derive(Debug, Args)]
+struct AgreementArgs {
+    /// The annotation files (the annotators can share a file or have their own)
+    #[arg(default_value = "annotations.toml")]
+    files: Vec<String>,
+    /// The number of logs with the lowest agreement to list
+    #[arg(short, long, default_value_t = 10)]
+    lowest: usize,
+    /// Only consider the lines selected with this label
+    #[arg(long)]
+    label: Option<String>,
 }
 
-fn main() -> io::Result<()> {
+fn main() -> Result<()> {
     let args = AppArgs::parse();
-    let log_paths = match args.paths {
-        None => list_log_paths(&args.dataset),
-        Some(file_path) => list_log_paths_from_file(&file_path)
-    };
+    let dataset = Dataset::new(&args.dataset, args.dataset_args.spec()?);
+    let parser = LogParser::new().format(args.format);
     match args.command {
-        Commands::Annotate(apollo_args) => apollo::bootstrap(apollo_args, &args.dataset, log_paths),
-        Commands::Visu(arthemis_args) => arthemis::bootstrap(arthemis_args, &args.dataset, log_paths),
+        Commands::Annotate(apollo_args) => {
+            let log_paths = log_paths(args.paths, &args.sampling, &dataset, &parser)?;
+            apollo::bootstrap(apollo_args, &dataset, log_paths, &parser)
+        }
+        Commands::Visu(arthemis_args) => {
+            let log_paths = log_paths(args.paths, &args.sampling, &dataset, &parser)?;
+            arthemis::bootstrap(arthemis_args, &dataset, log_paths, &parser)
+        }
+        Commands::Agreement(agreement_args) => print_agreement(agreement_args, &dataset, &parser),
+        Commands::Evaluate(evaluate_args) => evaluate::evaluate(evaluate_args, &dataset, &parser),
+    }
+}
+
+/// The paths of the log pairs to annotate or to visualise (only these commands need a sample): the
+/// given ones, the ones of the manifest of the current directory, or a new sample of the dataset.
+fn log_paths(
+    paths: Option<String>,
+    sampling: &Sampling,
+    dataset: &Dataset,
+    parser: &LogParser,
+) -> Result<Vec<PathBuf>> {
+    match paths {
+        None if Path::new(MANIFEST_FILE).exists() => {
+            let manifest = Manifest::load(MANIFEST_FILE, dataset, parser)?;
+            if manifest.sample.as_ref().map(|s| &s.sampling) != Some(sampling) {
+                bail!(
+                    "{} was not drawn with these sampling options, remove it to draw a new sample",
+                    MANIFEST_FILE
+                );
+            }
+            Ok(manifest.paths())
+        }
+        None => list_log_paths(dataset, &open_index(dataset)?, sampling, parser),
+        Some(file_path) => manifest::read_pairs(&file_path, dataset, parser, &open_index(dataset)?),
+    }
+}
+
+/// The annotations of an annotator, by log path.
+pub type Annotations = HashMap<String, LogAnnotation>;
+
+/// The annotation of a log by an annotator: the lines they selected, why the build failed
+/// according to them (the category of the failure and a free-text note), and the time they spent
+/// on it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "LogAnnotationFile", into = "LogAnnotationFile")]
+pub struct LogAnnotation {
+    pub selections: Vec<Selection>,
+    pub category: Option<String>,
+    pub note: String,
+    /// Each time the annotator opened the log
+    pub sessions: Vec<Session>,
+}
+
+impl LogAnnotation {
+    /// If the annotator wrote something about the log. A log that was only opened is not
+    /// annotated.
+    pub fn is_annotated(&self) -> bool {
+        !self.selections.is_empty() || self.category.is_some() || !self.note.is_empty()
+    }
+
+    /// If there is nothing to keep about the log.
+    pub fn is_empty(&self) -> bool {
+        !self.is_annotated() && self.sessions.is_empty()
+    }
+}
+
+/// A time an annotator had a log opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub opened: DateTime<Local>,
+    pub closed: DateTime<Local>,
+    /// The seconds the annotator was active (neither idle nor in another window)
+    pub active: u64,
+}
+
+/// The annotation of a log in a file: only its selections when it has no category nor note, as in
+/// the files written before there were notes.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum LogAnnotationFile {
+    Selections(Vec<Selection>),
+    Noted {
+        #[serde(default)]
+        selections: Vec<Selection>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        category: Option<String>,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        note: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        sessions: Vec<Session>,
+    },
+}
+
+impl From<LogAnnotationFile> for LogAnnotation {
+    fn from(annotation: LogAnnotationFile) -> Self {
+        match annotation {
+            LogAnnotationFile::Selections(selections) => LogAnnotation {
+                selections,
+                ..Default::default()
+            },
+            LogAnnotationFile::Noted {
+                selections,
+                category,
+                note,
+                sessions,
+            } => LogAnnotation {
+                selections,
+                category,
+                note,
+                sessions,
+            },
+        }
+    }
+}
+
+impl From<LogAnnotation> for LogAnnotationFile {
+    fn from(annotation: LogAnnotation) -> Self {
+        match annotation {
+            LogAnnotation {
+                selections,
+                category: None,
+                note,
+                sessions,
+            } if note.is_empty() && sessions.is_empty() => LogAnnotationFile::Selections(selections),
+            LogAnnotation {
+                selections,
+                category,
+                note,
+                sessions,
+            } => LogAnnotationFile::Noted {
+                selections,
+                category,
+                note,
+                sessions,
+            },
+        }
     }
 }
+
+/// A line selected by an annotator, with the label and the severity they gave it (if any).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "SelectionFile", into = "SelectionFile")]
+pub struct Selection {
+    pub line: usize,
+    pub label: Option<String>,
+    pub severity: Option<u8>,
+}
+
+impl Selection {
+    pub fn new(line: usize) -> Self {
+        Self {
+            line,
+            label: None,
+            severity: None,
+        }
+    }
+
+    /// If the selection has the label, or if there is no label to filter with.
+    pub fn has_label(&self, label: Option<&str>) -> bool {
+        label.is_none() || self.label.as_deref() == label
+    }
+}
+
+/// A selection in a file: only its line when it has no label nor severity, as in the files written
+/// before there were labels.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SelectionFile {
+    Line(usize),
+    Labeled {
+        line: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        severity: Option<u8>,
+    },
+}
+
+impl From<SelectionFile> for Selection {
+    fn from(selection: SelectionFile) -> Self {
+        match selection {
+            SelectionFile::Line(line) => Selection::new(line),
+            SelectionFile::Labeled {
+                line,
+                label,
+                severity,
+            } => Selection {
+                line,
+                label,
+                severity,
+            },
+        }
+    }
+}
+
+impl From<Selection> for SelectionFile {
+    fn from(selection: Selection) -> Self {
+        match selection {
+            Selection {
+                line,
+                label: None,
+                severity: None,
+            } => SelectionFile::Line(line),
+            Selection {
+                line,
+                label,
+                severity,
+            } => SelectionFile::Labeled {
+                line,
+                label,
+                severity,
+            },
+        }
+    }
+}
+
 pub enum WhatToDo {
     Exit,
     StayOnSameState,
     OpenFile((usize, usize)),
     ListDir,
 }
-/// List the paths containing the log pairs to annotate.
-/// It selects 100 paths.
-/// The vec is unsorted.
-pub fn list_log_paths(dataset_path: &str) -> Vec<PathBuf> {
-    // list every log pairs from the dataset
-    let mut all_paths = vec![];
-    let dataset_path = Path::new(dataset_path);
+/// Load the index of the dataset (building or refreshing it if needed).
+pub fn open_index(dataset: &Dataset) -> Result<DatasetIndex> {
     let bar = indicatif::ProgressBar::new_spinner();
-    bar.set_style(ProgressStyle::with_template("{spinner} {prefix} {msg}").unwrap());
-    bar.set_prefix("Listing dataset files: ");
-    let mut callback = |entry: &DirEntry| {
-        bar.tick();
-        let _ = entry.path().strip_prefix(dataset_path).map(|path| {
-            all_paths.push(path.to_path_buf());
-            bar.set_message(path.to_str().unwrap().to_string());
-        });
-    };
-    let _ = visit_dirs(dataset_path, &mut callback);
+    bar.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
+    bar.set_message("Indexing the dataset");
+    bar.enable_steady_tick(Duration::from_millis(100));
+    let (index, diagnostics) = DatasetIndex::open(dataset)?;
     bar.finish_and_clear();
-    // select 100 logs from the dataset
-    all_paths.sort();
-    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(123456789);
-    let mut paths = vec![];
-    while paths.len() < 100 {
-        let i = rng.gen_range(0..all_paths.len());
-        let p = &all_paths[i];
-        if !paths.contains(p) {
-            paths.push(p.to_path_buf());
+    dataset::print_diagnostics(&diagnostics);
+    Ok(index)
+}
+
+/// List the paths containing the log pairs to annotate.
+/// It draws a sample from the dataset, and saves it in `paths.txt` and in a manifest (with the
+/// hashes of the logs and the way they were drawn).
+/// The vec is unsorted.
+pub fn list_log_paths(
+    dataset: &Dataset,
+    index: &DatasetIndex,
+    sampling: &Sampling,
+    parser: &LogParser,
+) -> Result<Vec<PathBuf>> {
+    // the index is sorted, so the population is always in the same order
+    let population = index.accepted(&dataset.spec).collect::<Vec<_>>();
+    let sample = sampling.sample(&population)?;
+    let paths = sample.paths.clone();
+    Manifest::new(dataset, parser, &paths, Some(sample))?.save(MANIFEST_FILE)?;
+    let content = paths
+        .iter()
+        .map(|p| format!("{}\n", p.display()))
+        .collect::<String>();
+    atomic::write("paths.txt", content)?;
+    Ok(paths)
+}
+
+/// Print the agreement of the annotators on the logs annotated by at least two of them, overall
+/// and for the logs with the lowest agreement (according to Krippendorff's alpha).
+fn print_agreement(args: AgreementArgs, dataset: &Dataset, parser: &LogParser) -> Result<()> {
+    let mut annotators = BTreeMap::new();
+    for file in &args.files {
+        // there is no current annotator, the annotations of an old file are given to the file
+        for (annotator, annotations) in load_annotations(file, file)? {
+            if annotators.insert(annotator.clone(), annotations).is_some() {
+                bail!("the annotator {} is in several files", annotator);
+            }
         }
     }
-    let f = File::options()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open("paths.txt")
-        .unwrap();
-    let mut writer = BufWriter::new(f);
-    for ele in paths.iter() {
-        let _ = writeln!(writer, "{}", ele.to_str().unwrap());
+    let mut logs = annotators
+        .values()
+        .flat_map(|annotations| annotations.keys())
+        .filter(|log| {
+            annotators
+                .values()
+                .filter(|a| a.get(*log).is_some_and(LogAnnotation::is_annotated))
+                .count()
+                >= 2
+        })
+        .collect::<Vec<_>>();
+    logs.sort();
+    logs.dedup();
+
+    let mut overall = vec![];
+    let mut per_log = vec![];
+    for log in logs {
+        let failure_log = dataset.failure_log(log);
+        let lines = parser
+            .parse_file(&failure_log)
+            .with_context(|| format!("could not read {}", failure_log.display()))?
+            .len();
+        let selections = annotators
+            .values()
+            .map(|a| {
+                a.get(log).filter(|a| a.is_annotated()).map(|annotation| {
+                    annotation
+                        .selections
+                        .iter()
+                        .filter(|s| s.has_label(args.label.as_deref()))
+                        .map(|s| s.line)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let ratings =
+            agreement::line_ratings(lines, &selections.iter().map(Option::as_ref).collect::<Vec<_>>());
+        per_log.push((log, Agreement::of(&ratings)));
+        overall.extend(ratings);
     }
-    let _ = writer.flush();
-    paths
-}
+    // the undefined agreements (nothing to disagree on) are the highest
+    per_log.sort_by(|(_, a), (_, b)| match (a.krippendorff, b.krippendorff) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    });
 
-/// List the paths containing the log pairs to annotate from a file containing them
-pub fn list_log_paths_from_file(file_path: &str) -> Vec<PathBuf> {
-    if let Ok(content) = fs::read_to_string(file_path) {
-        content.lines().map(|p|Path::new(p).to_path_buf()).collect()
-    } else {
-        vec![]
+    let coefficient = |c: Option<f64>| c.map_or("-".to_string(), |c| format!("{:.3}", c));
+    let row = |name: &str, a: &Agreement| {
+        println!(
+            "{:<60} {:>8} {:>8} {:>12} {:>10} {:>8}",
+            name,
+            coefficient(a.cohen),
+            coefficient(a.fleiss),
+            coefficient(a.krippendorff),
+            a.annotators,
+            a.items
+        );
+    };
+    println!(
+        "{:<60} {:>8} {:>8} {:>12} {:>10} {:>8}",
+        "", "cohen", "fleiss", "krippendorff", "annotators", "lines"
+    );
+    row(
+        &format!("overall ({} logs)", per_log.len()),
+        &Agreement::of(&overall),
+    );
+    println!("\nlowest agreement:");
+    for (log, agreement) in per_log.iter().take(args.lowest) {
+        row(log, agreement);
     }
+    Ok(())
+}
+
+/// The annotations of a file, in their current format (by annotator) or in the format of the files
+/// written before there were several annotators.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnnotationFile {
+    ByAnnotator(BTreeMap<String, Annotations>),
+    SingleAnnotator(Annotations),
 }
 
-/// Load the annotations from a specific file.
-pub fn load_annotations(filename: &str) -> HashMap<String, Vec<usize>> {
+/// Load the annotations of every annotator from a specific file. There is no annotation yet if the
+/// file does not exist, but a file that can not be read is an error: it must not be overwritten by
+/// the next save.
+/// The annotations of a file written before there were several annotators are given to `annotator`.
+pub fn load_annotations(filename: &str, annotator: &str) -> Result<BTreeMap<String, Annotations>> {
     match fs::read_to_string(filename) {
-        Ok(content) => toml::from_str(&content).unwrap_or(HashMap::new()),
-        Err(_) => HashMap::new(),
+        Ok(content) => {
+            match toml::from_str(&content).with_context(|| format!("{} is corrupted", filename))? {
+                AnnotationFile::ByAnnotator(annotations) => Ok(annotations),
+                AnnotationFile::SingleAnnotator(annotations) => {
+                    Ok(BTreeMap::from([(annotator.to_string(), annotations)]))
+                }
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).with_context(|| format!("could not read {}", filename)),
     }
 }
 
-/// Save the annotations to the give file
-pub fn save_annotations(filename: &str, annotations: HashMap<String, Vec<usize>>, to_csv: bool) {
-    if let Ok(content) = toml::to_string(&annotations) {
-        let _ = fs::write(filename, content);
-    }
+/// Save the annotations of an annotator to the give file (and to a csv file with the same name if
+/// asked, with the notes in a `.notes.csv` file and the sessions in a `.times.csv` file), next to
+/// the annotations of the other annotators.
+/// The file is read again before, to keep what the other annotators saved in the meantime. The
+/// annotations of every annotator are returned.
+pub fn save_annotations(
+    filename: &str,
+    annotator: &str,
+    annotations: &Annotations,
+    to_csv: bool,
+) -> Result<BTreeMap<String, Annotations>> {
+    // the other annotators would be lost if a file that can not be read was overwritten
+    let mut all = load_annotations(filename, annotator)?;
+    all.insert(annotator.to_string(), annotations.clone());
+    atomic::write(filename, toml::to_string(&all)?)?;
     if to_csv {
-        // remove the .toml and replace it by a .csv
-        let filename = format!("{}.csv", &filename[..(filename.chars().count() - 5)]);
-        if let Ok(mut output) = csv::WriterBuilder::new().from_path(filename) {
-            let _ = output.write_record(["path", "type", "line"]);
-            for (log_path, selected_lines) in annotations {
-                for n in selected_lines {
-                    let _ = output.write_record([log_path.to_string(), "human".to_string(), n.to_string()]);
+        // replace the .toml by a .csv
+        let filename = Path::new(filename).with_extension("csv");
+        let mut output = csv::WriterBuilder::new().from_writer(vec![]);
+        output.write_record(["path", "type", "line", "label", "severity"])?;
+        for (annotator, annotations) in &all {
+            for (log_path, annotation) in annotations {
+                for selection in &annotation.selections {
+                    output.write_record([
+                        log_path.to_string(),
+                        format!("human:{}", annotator),
+                        selection.line.to_string(),
+                        selection.label.clone().unwrap_or_default(),
+                        selection.severity.map(|s| s.to_string()).unwrap_or_default(),
+                    ])?;
                 }
             }
-        };
-    }
-}
+        }
+        atomic::write(&filename, output.into_inner()?)?;
 
-/// Visit every directory recurively and call the callback when the directory contains the files
-/// `failure.log` and `succes.log` and both files are less than 1000 lines
-fn visit_dirs(dir: &Path, cb: &mut dyn FnMut(&DirEntry)) -> io::Result<()> {
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.join("success.log").exists() && path.join("failure.log").exists() {
-                if linecount::count_lines(fs::File::open(path.join("failure.log")).unwrap()).unwrap() <= 1000
-                {
-                    cb(&entry);
+        let mut notes = csv::WriterBuilder::new().from_writer(vec![]);
+        notes.write_record(["path", "type", "category", "note"])?;
+        for (annotator, annotations) in &all {
+            for (log_path, annotation) in annotations {
+                if annotation.category.is_some() || !annotation.note.is_empty() {
+                    notes.write_record([
+                        log_path.to_string(),
+                        format!("human:{}", annotator),
+                        annotation.category.clone().unwrap_or_default(),
+                        annotation.note.clone(),
+                    ])?;
                 }
-            } else {
-                visit_dirs(&path, cb)?;
             }
         }
+        atomic::write(filename.with_extension("notes.csv"), notes.into_inner()?)?;
+
+        let mut times = csv::WriterBuilder::new().from_writer(vec![]);
+        times.write_record(["path", "type", "opened", "closed", "active"])?;
+        for (annotator, annotations) in &all {
+            for (log_path, annotation) in annotations {
+                for session in &annotation.sessions {
+                    times.write_record([
+                        log_path.to_string(),
+                        format!("human:{}", annotator),
+                        session.opened.to_rfc3339(),
+                        session.closed.to_rfc3339(),
+                        session.active.to_string(),
+                    ])?;
+                }
+            }
+        }
+        atomic::write(filename.with_extension("times.csv"), times.into_inner()?)?;
     }
-    Ok(())
+    Ok(all)
 }
 
-/// Parse the file. By default, we remove the github timestamp at the begining of each line, and
-/// remove any ANSI escape code
-pub fn parse_file(file_content: String) -> Vec<String> {
-    let timestamp_regex = Regex::new(r"(?:\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}.\d{7}Z ?)?(.*)").unwrap();
-    let ansi_color_regex = Regex::new(r"\x1b?\[(?:\d+)?(?:;\d+)*m").unwrap();
-    let mut lines = vec![];
-    for line in file_content.lines() {
-        let caps = timestamp_regex.captures(line).unwrap();
-        let content = &caps[1];
-        let cleaned = ansi_color_regex.replace_all(content, "");
-        if !cleaned.trim().is_empty() {
-            lines.push(cleaned.to_string());
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selections_without_label_nor_severity_are_bare_lines() {
+        let labeled = Selection {
+            label: Some("error".to_string()),
+            severity: Some(3),
+            ..Selection::new(2)
+        };
+        let selections = vec![Selection::new(1), labeled];
+        let json = r#"[1,{"line":2,"label":"error","severity":3}]"#;
+        assert_eq!(serde_json::to_string(&selections).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Vec<Selection>>(json).unwrap(), selections);
+    }
+
+    #[test]
+    fn selections_of_the_files_written_before_the_labels_are_read() {
+        let annotations: Annotations = toml::from_str("\"owner/repo/run1-0\" = [4, 2]\n").unwrap();
+        assert_eq!(
+            annotations["owner/repo/run1-0"].selections,
+            vec![Selection::new(4), Selection::new(2)]
+        );
+    }
+
+    #[test]
+    fn annotations_without_category_nor_note_are_bare_selections() {
+        let mut annotation = LogAnnotation {
+            selections: vec![Selection::new(1)],
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_string(&annotation).unwrap(), "[1]");
+        annotation.note = "timeout".to_string();
+        let json = r#"{"selections":[1],"note":"timeout"}"#;
+        assert_eq!(serde_json::to_string(&annotation).unwrap(), json);
+        assert_eq!(serde_json::from_str::<LogAnnotation>(json).unwrap(), annotation);
+    }
+
+    #[test]
+    fn annotations_of_both_formats_are_read_from_the_same_file() {
+        let annotations: Annotations = toml::from_str(
+            "\"owner/repo/run1-0\" = [3]\n\
+             [\"owner/repo/run2-0\"]\n\
+             category = \"flaky\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            annotations["owner/repo/run1-0"].selections,
+            vec![Selection::new(3)]
+        );
+        assert_eq!(annotations["owner/repo/run1-0"].category, None);
+        let noted = &annotations["owner/repo/run2-0"];
+        assert!(noted.selections.is_empty() && noted.is_annotated());
+        assert_eq!(noted.category.as_deref(), Some("flaky"));
     }
-    lines
 }
@@ -0,0 +1,165 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    atomic,
+    dataset::{Dataset, Diagnostic},
+    index::DatasetIndex,
+    parser::LogParser,
+    sampling::Sample,
+    Result,
+};
+
+/// The name of the manifest written next to `paths.txt` when a sample is drawn.
+pub const MANIFEST_FILE: &str = "sample.toml";
+
+/// A pair of the sample, with what is needed to check that it did not change since it was sampled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestPair {
+    /// The path of the pair, relative to the dataset
+    pub path: PathBuf,
+    pub success_sha256: String,
+    pub failure_sha256: String,
+    /// The number of lines of the parsed failure log, i.e. the lines that can be annotated
+    pub lines: usize,
+}
+
+/// The pairs to annotate, with how they were drawn from the dataset. The annotations refer to the
+/// lines of the logs, so they are only valid for the exact logs of the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// How the pairs were drawn, `None` when they were given as a list
+    pub sample: Option<Sample>,
+    pub pairs: Vec<ManifestPair>,
+}
+
+impl Manifest {
+    /// Hash and parse the logs of the pairs (in parallel).
+    pub fn new(
+        dataset: &Dataset,
+        parser: &LogParser,
+        paths: &[PathBuf],
+        sample: Option<Sample>,
+    ) -> Result<Self> {
+        let pairs = paths
+            .par_iter()
+            .map(|path| pair(dataset, parser, path))
+            .collect::<Result<Vec<ManifestPair>>>()?;
+        Ok(Self { sample, pairs })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content =
+            fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("{} is not a valid manifest", path.display()))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        atomic::write(path, toml::to_string(self)?)
+    }
+
+    /// Read a manifest and check that the logs of the dataset are still the ones it was made with.
+    pub fn load<P: AsRef<Path>>(path: P, dataset: &Dataset, parser: &LogParser) -> Result<Self> {
+        let path = path.as_ref();
+        let manifest = Self::from_file(path)?;
+        let mismatches = manifest.verify(dataset, parser);
+        if mismatches.is_empty() {
+            Ok(manifest)
+        } else {
+            let mut message = format!(
+                "{}: {} pairs of the dataset changed since the manifest was made:",
+                path.display(),
+                mismatches.len()
+            );
+            for mismatch in mismatches {
+                message.push_str(&format!("\n  {}", mismatch));
+            }
+            Err(anyhow!(message))
+        }
+    }
+
+    /// The pairs whose logs are not the ones of the manifest anymore (or can not be read).
+    pub fn verify(&self, dataset: &Dataset, parser: &LogParser) -> Vec<Diagnostic> {
+        self.pairs
+            .par_iter()
+            .filter_map(|expected| {
+                let message = match pair(dataset, parser, &expected.path) {
+                    Err(e) => format!("{:#}", e),
+                    Ok(actual) if actual.success_sha256 != expected.success_sha256 => {
+                        format!("{} changed", dataset.spec.success_file)
+                    }
+                    Ok(actual) if actual.failure_sha256 != expected.failure_sha256 => {
+                        format!("{} changed", dataset.spec.failure_file)
+                    }
+                    Ok(actual) if actual.lines != expected.lines => format!(
+                        "{} lines once parsed instead of {} (was it parsed with another --format?)",
+                        actual.lines, expected.lines
+                    ),
+                    Ok(_) => return None,
+                };
+                Some(Diagnostic {
+                    path: expected.path.clone(),
+                    message,
+                })
+            })
+            .collect()
+    }
+
+    /// The paths of the pairs, in the order of the manifest.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.pairs.iter().map(|p| p.path.clone()).collect()
+    }
+}
+
+fn pair(dataset: &Dataset, parser: &LogParser, path: &Path) -> Result<ManifestPair> {
+    let failure_log = dataset.failure_log(path);
+    let read_error = |log: &Path| format!("could not read {}", log.display());
+    Ok(ManifestPair {
+        path: path.to_path_buf(),
+        success_sha256: sha256(&dataset.success_log(path))
+            .with_context(|| read_error(&dataset.success_log(path)))?,
+        failure_sha256: sha256(&failure_log).with_context(|| read_error(&failure_log))?,
+        lines: parser
+            .parse_file(&failure_log)
+            .with_context(|| read_error(&failure_log))?
+            .len(),
+    })
+}
+
+fn sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Read the pairs to use from a file: a manifest (`.toml`), which is verified, or a list of paths
+/// (one per line), of which only the paths in the dataset are kept.
+pub fn read_pairs<P: AsRef<Path>>(
+    path: P,
+    dataset: &Dataset,
+    parser: &LogParser,
+    index: &DatasetIndex,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    if path.extension().and_then(|e| e.to_str()) == Some("toml") {
+        return Ok(Manifest::load(path, dataset, parser)?.paths());
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    eprintln!(
+        "{} is a list of paths, the logs can not be checked against a manifest",
+        path.display()
+    );
+    let (paths, unknown) = index.partition_known(content.lines().map(PathBuf::from).collect());
+    for path in unknown {
+        eprintln!("{} is not in the dataset, ignored", path.display());
+    }
+    Ok(paths)
+}
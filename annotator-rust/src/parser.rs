@@ -0,0 +1,98 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    preprocess::LogFormat,
+    steps::{Command, Log},
+};
+
+/// A line of a log file, once cleaned by the [`LogParser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    /// The index of the line in the raw log file (starting at 0)
+    pub raw_number: usize,
+    /// The index of the line in the cleaned log (starting at 0). This is the line number used in
+    /// every annotation.
+    pub number: usize,
+    /// The prefix removed from the raw line (e.g. the github timestamp)
+    pub prefix: String,
+    /// The content of the line as displayed by a terminal, without the prefix and the escape
+    /// sequences
+    pub content: String,
+    /// The workflow command written by the CI in this line (e.g. `##[group]`)
+    pub command: Option<Command>,
+    /// The innermost section containing the line (see [`Log::sections`])
+    pub section: Option<usize>,
+}
+
+/// The parser used by every tool to read the logs, so that the line numbers are the same in every
+/// annotation.
+///
+/// By default, we detect the CI that produced the log and remove its prefix at the begining of
+/// each line (e.g. the github timestamp), render the escape sequences and carriage returns as a
+/// terminal would, and drop the empty lines.
+pub struct LogParser {
+    format: LogFormat,
+}
+
+impl LogParser {
+    pub fn new() -> Self {
+        Self {
+            format: LogFormat::Auto,
+        }
+    }
+
+    /// Force the format of the logs instead of detecting it.
+    pub fn format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Parse the content of a log file.
+    pub fn parse(&self, file_content: &str) -> Vec<LogLine> {
+        self.parse_log(file_content).lines
+    }
+
+    /// Parse the content of a log file, with the structure of its sections.
+    pub fn parse_log(&self, file_content: &str) -> Log {
+        let preprocessor = self
+            .format
+            .preprocessor()
+            .unwrap_or_else(|| LogFormat::detect(file_content));
+        let mut lines = vec![];
+        for (raw_number, line) in file_content.lines().enumerate() {
+            let (prefix, content) = preprocessor.split_prefix(line);
+            let content = preprocessor.visible(content);
+            if preprocessor.is_marker(&content) {
+                continue;
+            }
+            let cleaned = preprocessor.render(&content);
+            if !cleaned.trim().is_empty() {
+                lines.push(LogLine {
+                    raw_number,
+                    number: lines.len(),
+                    prefix: prefix.to_string(),
+                    command: preprocessor.command(&cleaned),
+                    content: cleaned,
+                    section: None,
+                });
+            }
+        }
+        Log::new(lines)
+    }
+
+    /// Read and parse a log file.
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<LogLine>> {
+        fs::read_to_string(path).map(|content| self.parse(&content))
+    }
+
+    /// Read and parse a log file, with the structure of its sections.
+    pub fn parse_log_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Log> {
+        fs::read_to_string(path).map(|content| self.parse_log(&content))
+    }
+}
+
+impl Default for LogParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,327 @@
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+use regex::Regex;
+
+use crate::{ansi, steps::Command};
+
+/// The number of lines looked at to detect the format of a log.
+const DETECTION_LINES: usize = 20;
+
+/// The CI specific cleaning of the raw lines of a log, done before rendering the escape sequences
+/// (see [`crate::ansi::render`]).
+pub trait Preprocessor {
+    /// The name of the format, as given on the command line
+    fn name(&self) -> &'static str;
+
+    /// Whether the first lines of a log look like they were produced by this CI
+    fn detect(&self, first_lines: &[&str]) -> bool;
+
+    /// Split a raw line into the prefix added by the CI (e.g. the timestamp) and the content of
+    /// the line
+    fn split_prefix<'a>(&self, line: &'a str) -> (&'a str, &'a str);
+
+    /// Remove the parts of the content that the CI does not display, and that are not escape
+    /// sequences (e.g. hidden notes)
+    fn visible<'a>(&self, content: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(content)
+    }
+
+    /// Whether the content is only a command for the CI log viewer, that is not a line of the log
+    fn is_marker(&self, _content: &str) -> bool {
+        false
+    }
+
+    /// The workflow command written by the CI in the cleaned line, if any
+    fn command(&self, _content: &str) -> Option<Command> {
+        None
+    }
+
+    /// Render the visible content as a terminal would display it
+    fn render(&self, content: &str) -> String {
+        ansi::render(content)
+    }
+}
+
+/// The format of the logs given to the tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Detect the format from the first lines of the log
+    #[default]
+    Auto,
+    Github,
+    Gitlab,
+    Jenkins,
+    Buildkite,
+    /// The github logs parsed as before the rendering of the escape sequences: only the timestamps
+    /// and the colors are removed, to keep the line numbers of the older annotations
+    Legacy,
+}
+
+impl LogFormat {
+    /// The preprocessor of the format, or `None` for [`LogFormat::Auto`].
+    pub fn preprocessor(self) -> Option<Box<dyn Preprocessor>> {
+        match self {
+            LogFormat::Auto => None,
+            LogFormat::Github => Some(Box::new(GitHub::new())),
+            LogFormat::Gitlab => Some(Box::new(GitLab::new())),
+            LogFormat::Jenkins => Some(Box::new(Jenkins::new())),
+            LogFormat::Buildkite => Some(Box::new(Buildkite::new())),
+            LogFormat::Legacy => Some(Box::new(Legacy::new())),
+        }
+    }
+
+    /// Find the preprocessor matching the first lines of the log. The logs of the dataset come
+    /// from github, so it is the format used when nothing else matches. The timestamps of github
+    /// are tried before the markers of jenkins, that a step of a github job can print too.
+    pub fn detect(file_content: &str) -> Box<dyn Preprocessor> {
+        let first_lines = file_content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .take(DETECTION_LINES)
+            .collect::<Vec<&str>>();
+        let candidates: [Box<dyn Preprocessor>; 4] = [
+            Box::new(Buildkite::new()),
+            Box::new(GitLab::new()),
+            Box::new(GitHub::new()),
+            Box::new(Jenkins::new()),
+        ];
+        candidates
+            .into_iter()
+            .find(|p| p.detect(&first_lines))
+            .unwrap_or_else(|| Box::new(GitHub::new()))
+    }
+}
+
+fn split_at_match<'a>(regex: &Regex, line: &'a str) -> (&'a str, &'a str) {
+    let end = regex.find(line).map(|m| m.end()).unwrap_or(0);
+    line.split_at(end)
+}
+
+/// GitHub Actions: each line starts with a `YYYY-MM-DDTHH:MM:SS.fffffffZ` timestamp, and the
+/// workflow commands are written as `##[group]`, `##[endgroup]`, `##[error]`...
+pub struct GitHub {
+    timestamp: Regex,
+}
+
+impl GitHub {
+    pub fn new() -> Self {
+        Self {
+            timestamp: Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}.\d{7}Z ?").unwrap(),
+        }
+    }
+}
+
+impl Default for GitHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Preprocessor for GitHub {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn detect(&self, first_lines: &[&str]) -> bool {
+        first_lines.iter().any(|l| self.timestamp.is_match(l))
+    }
+
+    fn split_prefix<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
+        split_at_match(&self.timestamp, line)
+    }
+
+    fn command(&self, content: &str) -> Option<Command> {
+        github_command(content)
+    }
+}
+
+fn github_command(content: &str) -> Option<Command> {
+    let (name, text) = content.strip_prefix("##[")?.split_once(']')?;
+    match name {
+        "group" => Some(Command::Group(text.to_string())),
+        "endgroup" => Some(Command::EndGroup),
+        "error" => Some(Command::Error),
+        "warning" => Some(Command::Warning),
+        "notice" => Some(Command::Notice),
+        _ => None,
+    }
+}
+
+/// GitLab CI: optional `<timestamp> <stream><O|E>[+]` prefix (runner 17+), and collapsible
+/// sections delimited by `section_start:<time>:<name>\r\e[0K` and `section_end:<time>:<name>\r\e[0K`.
+pub struct GitLab {
+    prefix: Regex,
+    detection: Regex,
+}
+
+impl GitLab {
+    pub fn new() -> Self {
+        Self {
+            prefix: Regex::new(
+                r"^(?:\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\.\d{6}Z [0-9a-f]{2}[OE]\+? ?)?(?:(?:\x1b\[0K)?section_(?:start|end):\d+:[^\r]*\r(?:\x1b\[0K)?)*",
+            )
+            .unwrap(),
+            detection: Regex::new(
+                r"section_start:\d+:|^Running with gitlab-runner|^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\.\d{6}Z [0-9a-f]{2}[OE]\+? ",
+            )
+            .unwrap(),
+        }
+    }
+}
+
+impl Default for GitLab {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Preprocessor for GitLab {
+    fn name(&self) -> &'static str {
+        "gitlab"
+    }
+
+    fn detect(&self, first_lines: &[&str]) -> bool {
+        first_lines.iter().any(|l| self.detection.is_match(l))
+    }
+
+    fn split_prefix<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
+        split_at_match(&self.prefix, line)
+    }
+}
+
+/// Jenkins: optional timestamper prefix (`[YYYY-MM-DDTHH:MM:SS.fffZ] ` or `HH:MM:SS `), and the
+/// hidden console notes (`\e[8mha:...\e[0m`) left in the raw console output.
+pub struct Jenkins {
+    prefix: Regex,
+    console_note: Regex,
+    detection: Regex,
+}
+
+impl Jenkins {
+    pub fn new() -> Self {
+        Self {
+            prefix: Regex::new(
+                r"^(?:\[\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?Z\] |\d{2}:\d{2}:\d{2}(?:\.\d+)? )?",
+            )
+            .unwrap(),
+            console_note: Regex::new(r"\x1b\[8mha:[^\x1b]*\x1b\[0m").unwrap(),
+            detection: Regex::new(r"^(?:\S+ )?(?:Started by |\[Pipeline\] )|\x1b\[8mha:").unwrap(),
+        }
+    }
+}
+
+impl Default for Jenkins {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Preprocessor for Jenkins {
+    fn name(&self) -> &'static str {
+        "jenkins"
+    }
+
+    fn detect(&self, first_lines: &[&str]) -> bool {
+        first_lines.iter().any(|l| self.detection.is_match(l))
+    }
+
+    fn split_prefix<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
+        split_at_match(&self.prefix, line)
+    }
+
+    fn visible<'a>(&self, content: &'a str) -> Cow<'a, str> {
+        self.console_note.replace_all(content, "")
+    }
+}
+
+/// Buildkite: timestamps are `\e_bk;t=<millis>\x07` sequences (sometimes without the `\e`), at the
+/// start of the line and after each `\r`, and `^^^ +++` lines are commands to expand the previous section.
+pub struct Buildkite {
+    timestamp: Regex,
+    prefix: Regex,
+}
+
+impl Buildkite {
+    pub fn new() -> Self {
+        Self {
+            timestamp: Regex::new(r"\x1b?_bk;t=\d+\x07").unwrap(),
+            prefix: Regex::new(r"^(?:\x1b?_bk;t=\d+\x07)*").unwrap(),
+        }
+    }
+}
+
+impl Default for Buildkite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Preprocessor for Buildkite {
+    fn name(&self) -> &'static str {
+        "buildkite"
+    }
+
+    fn detect(&self, first_lines: &[&str]) -> bool {
+        first_lines.iter().any(|l| self.timestamp.is_match(l))
+    }
+
+    fn split_prefix<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
+        split_at_match(&self.prefix, line)
+    }
+
+    fn visible<'a>(&self, content: &'a str) -> Cow<'a, str> {
+        self.timestamp.replace_all(content, "")
+    }
+
+    fn is_marker(&self, content: &str) -> bool {
+        content.trim() == "^^^ +++"
+    }
+}
+
+/// The parsing of the first annotations: the github timestamp and the color codes are removed, and
+/// the lines empty once cleaned are dropped, but the other escape sequences and the carriage
+/// returns are kept. The lines that only move the cursor (e.g. `\e[1G\e[0K`) are kept, so that
+/// the lines have the numbers they had in these annotations.
+pub struct Legacy {
+    timestamp: Regex,
+    color: Regex,
+}
+
+impl Legacy {
+    pub fn new() -> Self {
+        Self {
+            timestamp: Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}.\d{7}Z ?").unwrap(),
+            color: Regex::new(r"\x1b?\[(?:\d+)?(?:;\d+)*m").unwrap(),
+        }
+    }
+}
+
+impl Default for Legacy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Preprocessor for Legacy {
+    fn name(&self) -> &'static str {
+        "legacy"
+    }
+
+    /// Only used when asked for.
+    fn detect(&self, _first_lines: &[&str]) -> bool {
+        false
+    }
+
+    fn split_prefix<'a>(&self, line: &'a str) -> (&'a str, &'a str) {
+        split_at_match(&self.timestamp, line)
+    }
+
+    fn command(&self, content: &str) -> Option<Command> {
+        github_command(content)
+    }
+
+    fn render(&self, content: &str) -> String {
+        self.color.replace_all(content, "").into_owned()
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::bail;
+use clap::{Args, ValueEnum};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{index::IndexEntry, Result};
+
+/// The seed of the sample annotated for the experiment.
+pub const DEFAULT_SEED: u64 = 123456789;
+
+/// How the pairs to annotate are drawn from the dataset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Every pair has the same chance to be drawn
+    #[default]
+    Uniform,
+    /// The same number of pairs is drawn for each language (see `--languages`)
+    Language,
+    /// The same number of pairs is drawn for each repository
+    Repository,
+    /// The same number of pairs is drawn for each bucket of failure log length
+    Length,
+}
+
+/// The parameters of the sampling, recorded in the manifest of the sample.
+#[derive(Debug, Clone, PartialEq, Eq, Args, Serialize, Deserialize)]
+// the doc comment is not the description of the binaries
+#[command(about = None, long_about = None)]
+pub struct Sampling {
+    /// The number of pairs to draw from the dataset
+    #[arg(long = "sample-size", default_value_t = 100)]
+    pub size: usize,
+    /// The seed of the random generator
+    #[arg(long, default_value_t = DEFAULT_SEED)]
+    pub seed: u64,
+    #[arg(long, value_enum, default_value_t = Strategy::Uniform)]
+    pub strategy: Strategy,
+    /// The number of buckets of the `length` strategy, with the same number of pairs in each
+    #[arg(long, default_value_t = 4)]
+    pub length_buckets: usize,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Self {
+            size: 100,
+            seed: DEFAULT_SEED,
+            strategy: Strategy::Uniform,
+            length_buckets: 4,
+        }
+    }
+}
+
+/// The pairs drawn from the dataset, with how they were drawn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sample {
+    pub sampling: Sampling,
+    /// The number of pairs the sample was drawn from
+    pub population: usize,
+    /// The number of pairs drawn from each stratum (empty for the uniform strategy)
+    pub strata: BTreeMap<String, usize>,
+    #[serde(skip)]
+    pub paths: Vec<PathBuf>,
+}
+
+impl Sampling {
+    /// Draw the pairs from the population. The same population, seed and strategy always give the
+    /// same sample.
+    pub fn sample(&self, population: &[&IndexEntry]) -> Result<Sample> {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let groups = self.strata(population)?;
+        let paths = match self.strategy {
+            Strategy::Uniform => uniform(population, self.size, &mut rng),
+            _ => {
+                let sizes = groups.values().map(|g| g.len()).collect::<Vec<usize>>();
+                let allocation = allocate(&sizes, self.size, &mut rng);
+                let mut paths = vec![];
+                for (group, n) in groups.values().zip(allocation) {
+                    paths.extend(group.choose_multiple(&mut rng, n).map(|e| e.path.clone()));
+                }
+                paths
+            }
+        };
+        let strata = groups
+            .into_iter()
+            .map(|(key, group)| {
+                let n = group.iter().filter(|e| paths.contains(&e.path)).count();
+                (key, n)
+            })
+            .collect();
+        Ok(Sample {
+            sampling: self.clone(),
+            population: population.len(),
+            strata,
+            paths,
+        })
+    }
+
+    /// Group the population by the key of the strategy.
+    fn strata<'a>(&self, population: &[&'a IndexEntry]) -> Result<BTreeMap<String, Vec<&'a IndexEntry>>> {
+        let mut strata: BTreeMap<String, Vec<&IndexEntry>> = BTreeMap::new();
+        match self.strategy {
+            Strategy::Uniform => {}
+            Strategy::Language => {
+                if population.iter().all(|e| e.language.is_none()) {
+                    bail!(
+                        "the language of the pairs is unknown, give a csv of the languages with --languages"
+                    );
+                }
+                for entry in population {
+                    let language = entry.language.clone().unwrap_or_else(|| String::from("unknown"));
+                    strata.entry(language).or_default().push(entry);
+                }
+            }
+            Strategy::Repository => {
+                for entry in population {
+                    strata.entry(entry.repository()).or_default().push(entry);
+                }
+            }
+            Strategy::Length => {
+                let mut sorted = population.to_vec();
+                sorted.sort_by_key(|e| (e.failure.lines, e.path.clone()));
+                let buckets = self.length_buckets.max(1);
+                let n = sorted.len();
+                for i in 0..buckets {
+                    let bucket = &sorted[i * n / buckets..(i + 1) * n / buckets];
+                    if let (Some(first), Some(last)) = (bucket.first(), bucket.last()) {
+                        // zero padded to keep the buckets in order
+                        let key = format!("{:06}-{:06} lines", first.failure.lines, last.failure.lines);
+                        strata.entry(key).or_default().extend(bucket.iter().copied());
+                    }
+                }
+            }
+        }
+        Ok(strata)
+    }
+}
+
+/// Draw the pairs one by one, as it was done for the experiment (the population is sorted by path).
+fn uniform(population: &[&IndexEntry], size: usize, rng: &mut ChaCha8Rng) -> Vec<PathBuf> {
+    let size = size.min(population.len());
+    let mut paths = vec![];
+    while paths.len() < size {
+        let i = rng.gen_range(0..population.len());
+        let p = &population[i].path;
+        if !paths.contains(p) {
+            paths.push(p.to_path_buf());
+        }
+    }
+    paths
+}
+
+/// Split the size of the sample between the strata as evenly as possible: the strata smaller than
+/// their share are taken entirely, and the rest is split between the others. When there is less
+/// to draw than strata left, the strata drawing one more pair are chosen at random.
+fn allocate(sizes: &[usize], size: usize, rng: &mut ChaCha8Rng) -> Vec<usize> {
+    let mut allocation = vec![0; sizes.len()];
+    let mut remaining = size.min(sizes.iter().sum());
+    while remaining > 0 {
+        let mut open = (0..sizes.len())
+            .filter(|&i| allocation[i] < sizes[i])
+            .collect::<Vec<usize>>();
+        let share = remaining / open.len();
+        if share == 0 {
+            open.shuffle(rng);
+            for &i in &open[..remaining] {
+                allocation[i] += 1;
+            }
+            break;
+        }
+        for i in open {
+            let n = share.min(sizes[i] - allocation[i]);
+            allocation[i] += n;
+            remaining -= n;
+        }
+    }
+    allocation
+}
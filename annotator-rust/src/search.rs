@@ -0,0 +1,221 @@
+use anyhow::Context;
+use cidiff_annotations::{LogLine, Result};
+use ratatui::{
+    crossterm::event::KeyCode,
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use regex::Regex;
+
+/// A regex search in the displayed rows of a log, as in vim: typed after `/` (forward) or `?`
+/// (backward), the highlighted row follows the first match while the pattern is typed.
+/// The search is case insensitive when the pattern has no uppercase letter.
+#[derive(Default)]
+pub struct Search {
+    /// The pattern being typed
+    pattern: Option<String>,
+    regex: Option<Regex>,
+    forward: bool,
+    /// The highlighted row, the search and its direction when the typing started, to go back to
+    /// them when it is cancelled
+    origin: (usize, Option<Regex>, bool),
+}
+
+impl Search {
+    pub fn is_typing(&self) -> bool {
+        self.pattern.is_some()
+    }
+
+    /// The search being typed or the last one.
+    pub fn regex(&self) -> Option<&Regex> {
+        self.regex.as_ref()
+    }
+
+    /// Start typing a pattern from the highlighted row.
+    pub fn start(&mut self, forward: bool, highlighted: usize) {
+        self.origin = (highlighted, self.regex.clone(), self.forward);
+        self.pattern = Some(String::new());
+        self.forward = forward;
+    }
+
+    /// Handle a key pressed while typing the pattern: it returns the row to highlight.
+    /// An invalid pattern is an error once it is validated.
+    pub fn input(&mut self, key: KeyCode, lines: &[LogLine], rows: &[usize]) -> Result<usize> {
+        let Some(pattern) = self.pattern.as_mut() else {
+            return Ok(self.origin.0);
+        };
+        match key {
+            KeyCode::Char(c) => pattern.push(c),
+            KeyCode::Backspace if !pattern.is_empty() => {
+                pattern.pop();
+            }
+            KeyCode::Enter if !pattern.is_empty() => {
+                let pattern = self.pattern.take().unwrap_or_default();
+                if let Err(e) = compile(&pattern) {
+                    self.cancel();
+                    return Err(e);
+                }
+                return Ok(self
+                    .find(lines, rows, self.origin.0, self.forward)
+                    .unwrap_or(self.origin.0));
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Enter => {
+                self.cancel();
+                return Ok(self.origin.0);
+            }
+            _ => (),
+        }
+        self.regex = self
+            .pattern
+            .as_deref()
+            .filter(|p| !p.is_empty())
+            .and_then(|p| compile(p).ok());
+        Ok(self
+            .find(lines, rows, self.origin.0, self.forward)
+            .unwrap_or(self.origin.0))
+    }
+
+    fn cancel(&mut self) {
+        self.pattern = None;
+        (self.regex, self.forward) = (self.origin.1.take(), self.origin.2);
+    }
+
+    /// The next row matching the search after `from` (`n`), or before it when `reverse` (`N`),
+    /// wrapping around the log.
+    pub fn next(&self, lines: &[LogLine], rows: &[usize], from: usize, reverse: bool) -> Option<usize> {
+        self.find(lines, rows, from, self.forward != reverse)
+    }
+
+    fn find(&self, lines: &[LogLine], rows: &[usize], from: usize, forward: bool) -> Option<usize> {
+        let n = rows.len();
+        (1..=n)
+            .map(|i| {
+                if forward {
+                    (from + i) % n
+                } else {
+                    (from + n - i) % n
+                }
+            })
+            .find(|&row| self.matches(&lines[rows[row]]))
+    }
+
+    pub fn matches(&self, line: &LogLine) -> bool {
+        self.regex
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(&line.content))
+    }
+
+    /// The pattern being typed, with the number of rows it matches, to show in place of the
+    /// instructions.
+    pub fn prompt(&self, lines: &[LogLine], rows: &[usize]) -> Option<Line<'static>> {
+        let pattern = self.pattern.as_ref()?;
+        let status = match &self.regex {
+            _ if pattern.is_empty() => Span::raw(""),
+            Some(_) => Span::styled(
+                format!(
+                    "  [{} matches]",
+                    rows.iter().filter(|&&line| self.matches(&lines[line])).count()
+                ),
+                Style::default().fg(Color::DarkGray),
+            ),
+            None => Span::styled("  [invalid pattern]", Style::default().fg(Color::Red)),
+        };
+        Some(Line::from(vec![
+            Span::raw(format!("{}{}", if self.forward { "/" } else { "?" }, pattern)),
+            Span::styled("█", Style::default().fg(Color::Blue)),
+            status,
+        ]))
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    let case = if pattern.chars().any(char::is_uppercase) {
+        ""
+    } else {
+        "(?i)"
+    };
+    Regex::new(&format!("{}{}", case, pattern)).with_context(|| format!("invalid search {}", pattern))
+}
+
+/// Split the content of a line into the parts matching the search and the others, without its
+/// first `skip` characters (scrolled horizontally).
+pub fn split_matches(regex: Option<&Regex>, content: &str, skip: usize) -> Vec<(String, bool)> {
+    let mut parts = vec![];
+    let mut end = 0;
+    for m in regex.iter().flat_map(|regex| regex.find_iter(content)) {
+        parts.push((&content[end..m.start()], false));
+        parts.push((m.as_str(), true));
+        end = m.end();
+    }
+    parts.push((&content[end..], false));
+    let mut skip = skip;
+    parts
+        .into_iter()
+        .filter_map(|(part, matched)| {
+            let kept = part.chars().skip(skip).collect::<String>();
+            skip = skip.saturating_sub(part.chars().count());
+            (!kept.is_empty()).then_some((kept, matched))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use cidiff_annotations::LogParser;
+
+    use super::*;
+
+    /// Type the pattern then validate it, giving the highlighted row.
+    fn search(search: &mut Search, pattern: &str, lines: &[LogLine], rows: &[usize]) -> Result<usize> {
+        for c in pattern.chars() {
+            search.input(KeyCode::Char(c), lines, rows)?;
+        }
+        search.input(KeyCode::Enter, lines, rows)
+    }
+
+    #[test]
+    fn the_matches_are_found_around_the_log() {
+        let lines = LogParser::new()
+            .parse_log("build\nError: a\nok\nerror: b\nERROR c\n")
+            .lines;
+        let rows = (0..lines.len()).collect::<Vec<_>>();
+        let mut typed = Search::default();
+
+        typed.start(true, 0);
+        assert_eq!(search(&mut typed, "error", &lines, &rows).unwrap(), 1);
+        assert!(!typed.is_typing());
+        assert_eq!(typed.next(&lines, &rows, 1, false), Some(3));
+        assert_eq!(typed.next(&lines, &rows, 4, false), Some(1));
+        assert_eq!(typed.next(&lines, &rows, 1, true), Some(4));
+        // only the displayed rows are searched
+        assert_eq!(typed.next(&lines, &[0, 2, 4], 0, false), Some(2));
+
+        // with an uppercase letter, the case matters
+        typed.start(false, 3);
+        assert_eq!(search(&mut typed, "ERROR", &lines, &rows).unwrap(), 4);
+        assert!(!typed.matches(&lines[1]));
+
+        // a cancelled search goes back to the previous one
+        typed.start(true, 2);
+        assert_eq!(typed.input(KeyCode::Char('o'), &lines, &rows).unwrap(), 3);
+        assert_eq!(typed.input(KeyCode::Esc, &lines, &rows).unwrap(), 2);
+        assert!(typed.matches(&lines[4]) && !typed.matches(&lines[2]));
+        typed.start(true, 2);
+        assert!(search(&mut typed, "(", &lines, &rows).is_err());
+        assert!(typed.matches(&lines[4]) && !typed.is_typing());
+    }
+
+    #[test]
+    fn the_matches_are_split_after_the_scrolled_characters() {
+        let regex = Regex::new("b").unwrap();
+        assert_eq!(
+            split_matches(Some(&regex), "abcb", 1),
+            vec![
+                ("b".to_string(), true),
+                ("c".to_string(), false),
+                ("b".to_string(), true)
+            ]
+        );
+        assert_eq!(split_matches(None, "abc", 4), vec![]);
+    }
+}
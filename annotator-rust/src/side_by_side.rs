@@ -0,0 +1,139 @@
+use cidiff_annotations::{diff::Alignment, Dataset, LogLine, LogParser};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+};
+
+/// The success log of the opened pair, shown next to the failure log when asked.
+/// It is read when the log is opened, but aligned with the failure log the first time it is shown.
+pub struct SideBySide {
+    /// The lines of the success log, or why it could not be read
+    success: Result<Vec<LogLine>, String>,
+    alignment: Option<Alignment>,
+    shown: bool,
+}
+
+impl SideBySide {
+    pub fn new(parser: &LogParser, dataset: &Dataset, log_path: &str) -> Self {
+        let success_log = dataset.success_log(log_path);
+        Self {
+            success: parser
+                .parse_file(&success_log)
+                .map_err(|e| format!("could not read {}: {}", success_log.display(), e)),
+            alignment: None,
+            shown: false,
+        }
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    /// Show or hide the success log.
+    pub fn toggle(&mut self, failure: &[LogLine]) {
+        self.shown = !self.shown;
+        if let (true, None, Ok(success)) = (self.shown, &self.alignment, &self.success) {
+            let content = |lines: &[LogLine]| lines.iter().map(|l| l.content.clone()).collect::<Vec<_>>();
+            self.alignment = Some(Alignment::new(&content(success), &content(failure)));
+        }
+    }
+
+    /// Split the area of the failure log to make room for the success log on its left, if it is
+    /// shown: it returns the area of the success log and the area left to the failure log.
+    pub fn split(&self, area: Rect) -> (Option<Rect>, Rect) {
+        if !self.shown {
+            return (None, area);
+        }
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+        (Some(layout[0]), layout[1])
+    }
+
+    /// The success log, scrolled so that the line next to the highlighted line of the failure log
+    /// is on the same row.
+    pub fn widget(
+        &self,
+        failure_line: usize,
+        highlighted_row: usize,
+        line_start: usize,
+    ) -> SuccessWidget<'_> {
+        SuccessWidget {
+            side_by_side: self,
+            failure_line,
+            highlighted_row,
+            line_start,
+        }
+    }
+}
+
+pub struct SuccessWidget<'a> {
+    side_by_side: &'a SideBySide,
+    /// The highlighted line of the failure log
+    failure_line: usize,
+    /// The row of the highlighted line in the area of the failure log
+    highlighted_row: usize,
+    line_start: usize,
+}
+
+impl Widget for SuccessWidget<'_> {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let block = Block::new()
+            .borders(Borders::RIGHT)
+            .border_style(Style::new().fg(Color::DarkGray));
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let (lines, alignment) = match (&self.side_by_side.success, &self.side_by_side.alignment) {
+            (Ok(lines), Some(alignment)) => (lines, alignment),
+            (Err(e), _) => {
+                Paragraph::new(Span::styled(e.as_str(), Style::new().fg(Color::Red)))
+                    .wrap(Wrap { trim: false })
+                    .render(inner, buf);
+                return;
+            }
+            _ => return,
+        };
+        let counterpart = alignment.counterpart(self.failure_line);
+        let matched = alignment
+            .old_of_new
+            .get(self.failure_line)
+            .copied()
+            .flatten()
+            .is_some();
+        let width = lines.len().to_string().chars().count();
+        for i in 0..inner.height {
+            // the rows above the first line of the log are empty
+            let Some(index) = counterpart
+                .map(|c| c + i as usize)
+                .and_then(|l| l.checked_sub(self.highlighted_row))
+                .filter(|l| *l < lines.len())
+            else {
+                continue;
+            };
+            let style = if Some(index) == counterpart {
+                Style::new().bold().underlined()
+            } else {
+                Style::new()
+            };
+            // the lines that are not in the failure log
+            let (mark, style) = match alignment.new_of_old[index] {
+                _ if Some(index) == counterpart && !matched => (" ~ ", style.fg(Color::Yellow)),
+                Some(_) => ("   ", style),
+                None => (" - ", style.fg(Color::Red)),
+            };
+            let text: String = lines[index].content.chars().skip(self.line_start).collect();
+            let line = Line::from(vec![
+                Span::styled(format!("{:1$}", index, width), style.fg(Color::DarkGray)),
+                Span::styled(mark, style),
+                Span::styled(text, style),
+            ]);
+            buf.set_line(inner.x, inner.y + i, &line, inner.width);
+        }
+    }
+}
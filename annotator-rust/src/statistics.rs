@@ -0,0 +1,205 @@
+//! The statistics to know if the scores of the algorithms can be trusted, with only 100 logs: the
+//! confidence intervals of the scores, and the tests of the differences between two algorithms.
+//!
+//! The random resamplings take the random generator, so that they are reproducible with a seed.
+
+use rand::Rng;
+
+/// A confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub low: f64,
+    pub high: f64,
+}
+
+/// The result of a significance test: its statistic, and the probability of a difference at least
+/// as large if there is no difference (two-sided).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Test {
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+/// The percentile bootstrap confidence intervals of `N` statistics of the items (e.g. the logs):
+/// the items are resampled with replacement `resamples` times, and each statistic is computed on
+/// each resample. It is `None` without item or resample.
+pub fn bootstrap<T: Clone, const N: usize>(
+    items: &[T],
+    resamples: usize,
+    confidence: f64,
+    rng: &mut impl Rng,
+    statistics: impl Fn(&[T]) -> [f64; N],
+) -> Option<[Interval; N]> {
+    if items.is_empty() || resamples == 0 {
+        return None;
+    }
+    let mut values = vec![Vec::with_capacity(resamples); N];
+    let mut resample = Vec::with_capacity(items.len());
+    for _ in 0..resamples {
+        resample.clear();
+        resample.extend((0..items.len()).map(|_| items[rng.gen_range(0..items.len())].clone()));
+        for (values, value) in values.iter_mut().zip(statistics(&resample)) {
+            values.push(value);
+        }
+    }
+    let alpha = (1.0 - confidence) / 2.0;
+    Some(std::array::from_fn(|i| {
+        values[i].sort_by(f64::total_cmp);
+        Interval {
+            low: quantile(&values[i], alpha),
+            high: quantile(&values[i], 1.0 - alpha),
+        }
+    }))
+}
+
+/// The quantile of sorted values, interpolated between the closest ones.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (position - below as f64)
+}
+
+/// The Wilcoxon signed-rank test of paired differences (e.g. the difference of F1 of two algorithms
+/// on each log). The null differences are dropped, and the ties get their average rank.
+/// The statistic is the smallest sum of ranks (of the positive or of the negative differences), and
+/// the p-value is from the normal approximation, with the correction for ties and continuity. It
+/// is `None` when every difference is null.
+pub fn wilcoxon_signed_rank(differences: &[f64]) -> Option<Test> {
+    let mut differences = differences
+        .iter()
+        .copied()
+        .filter(|d| *d != 0.0)
+        .collect::<Vec<_>>();
+    differences.sort_by(|a, b| a.abs().total_cmp(&b.abs()));
+    let n = differences.len() as f64;
+    let mut positive = 0.0;
+    let mut ties = 0.0;
+    let mut start = 0;
+    while start < differences.len() {
+        let end = start
+            + differences[start..]
+                .iter()
+                .take_while(|d| d.abs() == differences[start].abs())
+                .count();
+        // the ranks start at 1
+        let rank = (start + end + 1) as f64 / 2.0;
+        positive += rank * differences[start..end].iter().filter(|d| **d > 0.0).count() as f64;
+        let t = (end - start) as f64;
+        ties += t * t * t - t;
+        start = end;
+    }
+    let mean = n * (n + 1.0) / 4.0;
+    let variance = n * (n + 1.0) * (2.0 * n + 1.0) / 24.0 - ties / 48.0;
+    if variance <= 0.0 {
+        return None;
+    }
+    let z = ((positive - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+    Some(Test {
+        statistic: positive.min(n * (n + 1.0) / 2.0 - positive),
+        p_value: normal_p_value(z),
+    })
+}
+
+/// McNemar's test of the decisions of two algorithms on the same items (e.g. the lines of the
+/// logs), from the number of items that only the first one got right and that only the second one
+/// got right. The statistic is the chi-squared with the continuity correction; the p-value is
+/// exact (binomial) when there are less than 25 discordant items, and from the chi-squared
+/// otherwise. It is `None` when they always agree.
+pub fn mcnemar(only_first: usize, only_second: usize) -> Option<Test> {
+    let n = only_first + only_second;
+    if n == 0 {
+        return None;
+    }
+    let difference = only_first.abs_diff(only_second) as f64;
+    let statistic = (difference - 1.0).max(0.0).powi(2) / n as f64;
+    let p_value = if n < 25 {
+        // twice the probability of the smallest count, in the binomial of n fair trials
+        let mut coefficient = 0.0_f64;
+        let mut probability = 0.0;
+        for k in 0..=only_first.min(only_second) {
+            if k > 0 {
+                coefficient += ((n - k + 1) as f64).ln() - (k as f64).ln();
+            }
+            probability += (coefficient - n as f64 * 2.0_f64.ln()).exp();
+        }
+        (2.0 * probability).min(1.0)
+    } else {
+        // a chi-squared with one degree of freedom is the square of a standard normal
+        normal_p_value(statistic.sqrt())
+    };
+    Some(Test { statistic, p_value })
+}
+
+/// The paired permutation test of differences: the sign of each difference is flipped at random,
+/// as if the two algorithms were exchangeable on each item. The statistic is the mean difference.
+/// Every sign is tried when there are fewer combinations than `permutations`. It is `None` without
+/// any difference.
+pub fn paired_permutation(differences: &[f64], permutations: usize, rng: &mut impl Rng) -> Option<Test> {
+    if differences.is_empty() {
+        return None;
+    }
+    let n = differences.len();
+    let observed = differences.iter().sum::<f64>();
+    // a relative tolerance, for the sums in another order
+    let tolerance = 1e-9 * differences.iter().map(|d| d.abs()).sum::<f64>();
+    let is_extreme = |sum: f64| sum.abs() >= observed.abs() - tolerance;
+    let p_value = if n < usize::BITS as usize && 1 << n <= permutations {
+        let extreme = (0..1_usize << n)
+            .filter(|signs| {
+                let sum = differences
+                    .iter()
+                    .enumerate()
+                    .map(|(i, d)| if signs & (1 << i) == 0 { *d } else { -d })
+                    .sum();
+                is_extreme(sum)
+            })
+            .count();
+        extreme as f64 / (1_usize << n) as f64
+    } else {
+        let extreme = (0..permutations)
+            .filter(|_| {
+                let sum = differences.iter().map(|d| if rng.gen() { *d } else { -d }).sum();
+                is_extreme(sum)
+            })
+            .count();
+        // the observed differences are one of the permutations
+        (extreme + 1) as f64 / (permutations + 1) as f64
+    };
+    Some(Test {
+        statistic: observed / n as f64,
+        p_value,
+    })
+}
+
+/// The two-sided p-value of a standard normal.
+fn normal_p_value(z: f64) -> f64 {
+    // the approximation is slightly above 1 at 0
+    erfc(z.abs() / std::f64::consts::SQRT_2).min(1.0)
+}
+
+/// The complementary error function, with a fractional error below 1.2e-7 (from Numerical Recipes).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |acc, c| c + t * acc);
+    let r = t * (-z * z + polynomial).exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+
+use crate::parser::LogLine;
+
+/// A workflow command written by the CI in the log (e.g. `##[group]` on github).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Start of a collapsible group, with its name
+    Group(String),
+    EndGroup,
+    Error,
+    Warning,
+    Notice,
+}
+
+/// The name of the groups opened by github at the beginning of each step (with the command run by
+/// the step).
+const STEP_PREFIX: &str = "Run ";
+
+/// A group of lines of the log. The top level sections are the steps of the job: they start at the
+/// group opened by github at the beginning of the step (or at any group opened before the first
+/// step) and last until the next step, while the nested sections end at their `##[endgroup]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    /// The number of the line opening the section
+    pub start: usize,
+    /// The number of the last line of the section (included)
+    pub end: usize,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+/// A parsed log, with the tree of its sections.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Log {
+    pub lines: Vec<LogLine>,
+    /// Every section of the log, in the order they are opened. The sections refer to each other
+    /// (and the lines refer to them) by their index in this vec.
+    pub sections: Vec<Section>,
+}
+
+impl Log {
+    /// Build the tree of sections from the commands of the lines, and tag each line with its
+    /// innermost section.
+    pub fn new(mut lines: Vec<LogLine>) -> Self {
+        let mut sections: Vec<Section> = vec![];
+        // the groups not closed yet
+        let mut open: Vec<usize> = vec![];
+        let mut step = None;
+        for line in lines.iter_mut() {
+            match &line.command {
+                Some(Command::Group(name)) => {
+                    let parent = match open.last() {
+                        Some(group) => Some(*group),
+                        None if name.starts_with(STEP_PREFIX) => None,
+                        None => step,
+                    };
+                    let index = sections.len();
+                    sections.push(Section {
+                        name: name.clone(),
+                        start: line.number,
+                        end: line.number,
+                        parent,
+                        children: vec![],
+                    });
+                    match parent {
+                        Some(parent) => sections[parent].children.push(index),
+                        None => step = Some(index),
+                    }
+                    open.push(index);
+                    line.section = Some(index);
+                }
+                Some(Command::EndGroup) => {
+                    line.section = open.pop().or(step);
+                }
+                _ => line.section = open.last().copied().or(step),
+            }
+            // extend the section and its parents up to this line
+            let mut section = line.section;
+            while let Some(s) = section {
+                sections[s].end = line.number;
+                section = sections[s].parent;
+            }
+        }
+        Self { lines, sections }
+    }
+
+    /// The step (top level section) containing the line.
+    pub fn step_of(&self, line: usize) -> Option<usize> {
+        let mut section = self.lines.get(line)?.section?;
+        while let Some(parent) = self.sections[section].parent {
+            section = parent;
+        }
+        Some(section)
+    }
+
+    /// The steps of the log (the top level sections).
+    pub fn steps(&self) -> impl Iterator<Item = (usize, &Section)> {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.parent.is_none())
+    }
+
+    /// The lines to display when the given sections are folded: a folded section only shows the
+    /// line opening it.
+    pub fn visible_lines(&self, folded: &HashSet<usize>) -> Vec<usize> {
+        let mut visible = vec![];
+        let mut number = 0;
+        while number < self.lines.len() {
+            visible.push(number);
+            // skip the content of the outermost folded section starting here
+            let skip_to = self
+                .sections
+                .iter()
+                .enumerate()
+                .filter(|(i, s)| s.start == number && folded.contains(i))
+                .map(|(_, s)| s.end)
+                .max();
+            number = skip_to.unwrap_or(number) + 1;
+        }
+        visible
+    }
+}
+
+/// The sections of a log folded by the user, with the lines left displayed (one per row).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Folds {
+    folded: HashSet<usize>,
+    /// The line numbers displayed in each row, i.e. the lines not hidden in a folded section
+    rows: Vec<usize>,
+}
+
+impl Folds {
+    /// Nothing folded: each line of the log is a row.
+    pub fn new(log: &Log) -> Self {
+        Self {
+            folded: HashSet::new(),
+            rows: (0..log.lines.len()).collect(),
+        }
+    }
+
+    pub fn rows(&self) -> &[usize] {
+        &self.rows
+    }
+
+    /// Fold or unfold the innermost section containing the line of the row, and give the row to
+    /// highlight after it (the row of the line opening the section).
+    pub fn toggle(&mut self, log: &Log, row: usize) -> usize {
+        let Some(line) = self.rows.get(row).copied() else {
+            return row;
+        };
+        match log.lines[line].section {
+            Some(section) => {
+                if !self.folded.remove(&section) {
+                    self.folded.insert(section);
+                }
+                self.refresh(log, log.sections[section].start)
+            }
+            None => row,
+        }
+    }
+
+    /// Fold every step, or unfold everything if something is already folded, and give the row to
+    /// highlight after it (the row of the line of the row, or of its step when it is folded).
+    pub fn toggle_all(&mut self, log: &Log, row: usize) -> usize {
+        let Some(line) = self.rows.get(row).copied() else {
+            return row;
+        };
+        if self.folded.is_empty() {
+            self.folded = log.steps().map(|(i, _)| i).collect();
+            let step = log.step_of(line);
+            self.refresh(log, step.map(|s| log.sections[s].start).unwrap_or(line))
+        } else {
+            self.folded.clear();
+            self.refresh(log, line)
+        }
+    }
+
+    /// Compute the rows after a fold, and give the row of the given line (or the closest row before
+    /// it if the line is hidden).
+    fn refresh(&mut self, log: &Log, line: usize) -> usize {
+        self.rows = log.visible_lines(&self.folded);
+        self.rows.partition_point(|l| *l <= line).saturating_sub(1)
+    }
+}
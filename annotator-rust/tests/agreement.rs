@@ -0,0 +1,73 @@
+use cidiff_annotations::agreement::{self, Agreement};
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("the coefficient is not defined");
+    assert!(
+        (actual - expected).abs() < 1e-3,
+        "{} instead of {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn two_annotators() {
+    // 50 items: both say yes for 20, only the first for 5, only the second for 10, none for 15
+    let mut ratings = vec![];
+    for (first, second, n) in [
+        (true, true, 20),
+        (true, false, 5),
+        (false, true, 10),
+        (false, false, 15),
+    ] {
+        ratings.extend(std::iter::repeat_n(vec![Some(first), Some(second)], n));
+    }
+    let agreement = Agreement::of(&ratings);
+    assert_close(agreement.cohen, 0.4);
+    assert_close(agreement.krippendorff, 0.4);
+    assert_close(agreement.fleiss, 0.394);
+    assert_eq!((agreement.annotators, agreement.items), (2, 50));
+}
+
+#[test]
+fn fleiss_kappa_with_many_annotators() {
+    // 10 items rated by 14 annotators in 5 categories: the number of annotators for each category
+    let counts = [
+        [0, 0, 0, 0, 14],
+        [0, 2, 6, 4, 2],
+        [0, 0, 3, 5, 6],
+        [0, 3, 9, 2, 0],
+        [2, 2, 8, 1, 1],
+        [7, 7, 0, 0, 0],
+        [3, 2, 6, 3, 0],
+        [2, 5, 3, 2, 2],
+        [6, 5, 2, 1, 0],
+        [0, 2, 2, 3, 7],
+    ];
+    let ratings = counts
+        .iter()
+        .map(|item| {
+            item.iter()
+                .enumerate()
+                .flat_map(|(category, &n)| std::iter::repeat_n(Some(category), n))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_close(agreement::fleiss_kappa(&ratings), 0.210);
+}
+
+#[test]
+fn the_lines_not_annotated_are_missing_ratings() {
+    let first = vec![1, 3];
+    let second = vec![1];
+    let ratings = agreement::line_ratings(4, &[Some(&first), Some(&second), None]);
+    assert_eq!(ratings[1], vec![Some(true), Some(true), None]);
+    assert_eq!(ratings[3], vec![Some(true), Some(false), None]);
+    let agreement = Agreement::of(&ratings);
+    assert_eq!((agreement.annotators, agreement.items), (2, 4));
+
+    // everybody agrees that nothing is relevant: there is no disagreement to expect
+    let ratings = agreement::line_ratings(4, &[Some(&vec![]), Some(&vec![])]);
+    assert_eq!(Agreement::of(&ratings).krippendorff, None);
+    assert_eq!(Agreement::of(&ratings).cohen, None);
+}
@@ -0,0 +1,22 @@
+use std::fs;
+
+use cidiff_annotations::atomic;
+
+#[test]
+fn an_atomic_write_replaces_the_file() {
+    let dir = std::env::temp_dir().join(format!("cidiff-atomic-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("annotations.toml");
+
+    atomic::write(&path, "old").unwrap();
+    atomic::write(&path, "new").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    // the temporary file is renamed, nothing is left next to the file
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    assert!(atomic::write(dir.join("missing/annotations.toml"), "new").is_err());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,85 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use cidiff_annotations::{index::INDEX_FILE, Dataset, DatasetIndex, DatasetSpec, LogParser, Manifest};
+
+/// A new empty dataset in the temporary directory.
+fn dataset(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cidiff-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn write_pair(dataset: &Path, pair: &str, success_lines: usize, failure_lines: usize) {
+    let dir = dataset.join(pair);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("success.log"), "ok\n".repeat(success_lines)).unwrap();
+    fs::write(dir.join("failure.log"), "ko\n".repeat(failure_lines)).unwrap();
+}
+
+#[test]
+fn the_index_is_refreshed_when_the_dataset_changes() {
+    let path = dataset("index");
+    write_pair(&path, "owner/repo/run1-0", 3, 4);
+    write_pair(&path, "owner/repo/run2-0", 5, 2000);
+    fs::create_dir_all(path.join("owner/repo/incomplete")).unwrap();
+    let dataset = Dataset::new(&path, DatasetSpec::default());
+
+    let (index, diagnostics) = DatasetIndex::open(&dataset).unwrap();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    assert!(path.join(INDEX_FILE).is_file());
+    let lines = index
+        .entries
+        .iter()
+        .map(|e| (e.path.to_str().unwrap(), e.success.lines, e.failure.lines))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![("owner/repo/run1-0", 3, 4), ("owner/repo/run2-0", 5, 2000)]
+    );
+    assert_eq!(index.entries[0].repository(), "owner/repo");
+    // the failure log of the second pair is over the limit of the default spec
+    assert_eq!(index.accepted(&dataset.spec).count(), 1);
+
+    write_pair(&path, "owner/other/run3-0", 1, 1);
+    fs::write(path.join("owner/repo/run1-0/failure.log"), "ko\nko\n").unwrap();
+    let (index, _) = DatasetIndex::open(&dataset).unwrap();
+    assert_eq!(index.entries.len(), 3);
+    assert_eq!(index.get("owner/other/run3-0").unwrap().failure.lines, 1);
+    assert_eq!(index.get("owner/repo/run1-0").unwrap().failure.lines, 2);
+
+    fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn a_manifest_is_rejected_when_a_log_changed() {
+    let path = dataset("manifest");
+    write_pair(&path, "owner/repo/run1-0", 3, 4);
+    write_pair(&path, "owner/repo/run2-0", 5, 6);
+    let dataset = Dataset::new(&path, DatasetSpec::default());
+    let parser = LogParser::new();
+    let pairs = vec![
+        PathBuf::from("owner/repo/run2-0"),
+        PathBuf::from("owner/repo/run1-0"),
+    ];
+    let manifest_path = path.join("sample.toml");
+    Manifest::new(&dataset, &parser, &pairs, None)
+        .unwrap()
+        .save(&manifest_path)
+        .unwrap();
+
+    let manifest = Manifest::load(&manifest_path, &dataset, &parser).unwrap();
+    assert_eq!(manifest.paths(), pairs);
+    assert_eq!(manifest.pairs[0].lines, 6);
+
+    fs::write(path.join("owner/repo/run1-0/failure.log"), "ko\nok\nko\nko\n").unwrap();
+    let mismatches = manifest.verify(&dataset, &parser);
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].path, PathBuf::from("owner/repo/run1-0"));
+    assert!(Manifest::load(&manifest_path, &dataset, &parser).is_err());
+
+    fs::remove_dir_all(&path).unwrap();
+}
@@ -0,0 +1,42 @@
+use cidiff_annotations::diff::{self, Alignment};
+
+#[test]
+fn longest_common_subsequence() {
+    let old = "ABCBDAB".chars().collect::<Vec<_>>();
+    let new = "BDCABA".chars().collect::<Vec<_>>();
+    let pairs = diff::lcs(&old, &new);
+    assert_eq!(pairs.len(), 4);
+    assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+    assert!(pairs.iter().all(|&(o, n)| old[o] == new[n]));
+
+    assert_eq!(diff::lcs(&old, &old), (0..7).map(|i| (i, i)).collect::<Vec<_>>());
+    assert_eq!(diff::lcs(&old, &[]), vec![]);
+}
+
+#[test]
+fn the_changed_lines_are_next_to_the_lines_they_replace() {
+    let old = ["setup", "test a", "test b", "ok", "done"];
+    let new = [
+        "setup", "test a", "error 1", "error 2", "error 3", "failed", "done",
+    ];
+    let alignment = Alignment::new(&old, &new);
+    assert_eq!(
+        alignment.old_of_new,
+        vec![Some(0), Some(1), None, None, None, None, Some(4)]
+    );
+    assert_eq!(alignment.new_of_old, vec![Some(0), Some(1), None, None, Some(6)]);
+    // "test b" and "ok" are replaced by the 4 lines of the failure
+    let counterparts = (0..new.len())
+        .map(|n| alignment.counterpart(n))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        counterparts,
+        vec![Some(0), Some(1), Some(2), Some(3), Some(3), Some(3), Some(4)]
+    );
+
+    // the added lines are next to the line before them
+    let alignment = Alignment::new(&["a", "b"], &["x", "a", "y", "b"]);
+    let counterparts = (0..4).map(|n| alignment.counterpart(n)).collect::<Vec<_>>();
+    assert_eq!(counterparts, vec![Some(0), Some(0), Some(0), Some(1)]);
+    assert_eq!(Alignment::new::<&str>(&[], &["a"]).counterpart(0), None);
+}
@@ -0,0 +1,286 @@
+use std::{collections::HashSet, fs};
+
+use cidiff_annotations::{
+    evaluation::{
+        self, load_selections, Confusion, Evaluations, Metric, RankingMeans, RankingScores, RegionCounts,
+        Scores, Settings, NO_STEP,
+    },
+    Dataset, DatasetSpec, LogParser,
+};
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-3,
+        "{} instead of {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn confusion_of_a_log() {
+    let reference = HashSet::from([3, 4, 5, 6]);
+    let selected = HashSet::from([5, 6, 7]);
+    let confusion = Confusion::of(20, &reference, &selected);
+    assert_eq!(
+        confusion,
+        Confusion {
+            true_positives: 2,
+            false_positives: 1,
+            false_negatives: 2,
+            true_negatives: 15,
+        }
+    );
+    assert_close(confusion.precision(), 2.0 / 3.0);
+    assert_close(confusion.recall(), 0.5);
+    assert_close(confusion.f1(), 4.0 / 7.0);
+    // the recall is twice as important
+    assert_close(confusion.f_beta(2.0), 10.0 / 19.0);
+
+    // nothing selected: nothing is right
+    let confusion = Confusion::of(20, &reference, &HashSet::new());
+    assert_eq!(
+        (confusion.precision(), confusion.recall(), confusion.f1()),
+        (0.0, 0.0, 0.0)
+    );
+    assert_eq!(confusion.true_negatives, 16);
+}
+
+#[test]
+fn micro_and_macro_averages() {
+    // a short log found perfectly, and a long one half found
+    let short = Confusion {
+        true_positives: 1,
+        false_positives: 0,
+        false_negatives: 0,
+        true_negatives: 9,
+    };
+    let long = Confusion {
+        true_positives: 5,
+        false_positives: 5,
+        false_negatives: 5,
+        true_negatives: 85,
+    };
+    let micro = Scores::micro(&[short, long], 1.0);
+    assert_close(micro.precision, 6.0 / 11.0);
+    assert_close(micro.recall, 6.0 / 11.0);
+    let macro_average = Scores::macro_average(&[short, long], 1.0);
+    assert_close(macro_average.precision, 0.75);
+    assert_close(macro_average.recall, 0.75);
+    assert_close(macro_average.f1, 0.75);
+    assert_eq!([short, long].iter().sum::<Confusion>().true_negatives, 94);
+}
+
+#[test]
+fn discordant_lines() {
+    let reference = HashSet::from([1, 2, 3]);
+    // 1 and 4 are right for the first one only, 3 and 5 for the second one only
+    let first = HashSet::from([1, 2, 5]);
+    let second = HashSet::from([2, 3, 4]);
+    assert_eq!(evaluation::discordant(&reference, &first, &second), (2, 2));
+    assert_eq!(evaluation::discordant(&reference, &reference, &second), (2, 0));
+    assert_eq!(evaluation::discordant(&reference, &first, &first), (0, 0));
+}
+
+#[test]
+fn tolerance_window() {
+    // the line above the error message, and one line selected for two relevant lines
+    let reference = HashSet::from([10, 11, 30]);
+    let selected = HashSet::from([9, 31, 50]);
+    assert_eq!(
+        evaluation::matching(&reference, &selected, 1),
+        vec![(10, 9), (30, 31)]
+    );
+    let confusion = Confusion::within(100, &reference, &selected, 1);
+    assert_eq!(
+        (
+            confusion.true_positives,
+            confusion.false_positives,
+            confusion.false_negatives
+        ),
+        (2, 1, 1)
+    );
+    assert_eq!(
+        Confusion::within(100, &reference, &selected, 0),
+        Confusion::of(100, &reference, &selected)
+    );
+    assert_eq!(
+        evaluation::near_misses(&reference, &selected, 1),
+        HashSet::from([9, 31])
+    );
+
+    // the relevant lines that are selected are matched first, and only moved to match more lines
+    let reference = HashSet::from([6]);
+    let selected = HashSet::from([5, 6]);
+    assert_eq!(evaluation::matching(&reference, &selected, 1), vec![(6, 6)]);
+    assert_eq!(evaluation::near_misses(&reference, &selected, 1), HashSet::new());
+    let reference = HashSet::from([1, 2]);
+    let selected = HashSet::from([2, 3]);
+    assert_eq!(
+        evaluation::matching(&reference, &selected, 1),
+        vec![(1, 2), (2, 3)]
+    );
+    assert_eq!(
+        evaluation::near_misses(&reference, &selected, 1),
+        HashSet::from([3])
+    );
+    let reference = HashSet::from([10, 11]);
+    let selected = HashSet::from([11, 12]);
+    assert_eq!(
+        evaluation::matching(&reference, &selected, 1),
+        vec![(10, 11), (11, 12)]
+    );
+    let confusion = Confusion::within(20, &reference, &selected, 1);
+    assert_eq!((confusion.true_positives, confusion.false_positives), (2, 0));
+    // the relevant lines can be moved along a chain of windows
+    let reference = HashSet::from([1, 2, 3]);
+    let selected = HashSet::from([2, 3, 4]);
+    assert_eq!(
+        evaluation::matching(&reference, &selected, 1),
+        vec![(1, 2), (2, 3), (3, 4)]
+    );
+}
+
+#[test]
+fn ranking_scores() {
+    let reference = HashSet::from([10, 20]);
+    // two lines read before the first relevant one
+    let scores = RankingScores::of(&reference, &[1, 2, 10, 3, 20], &[1, 3, 10]);
+    assert_eq!(scores.precision_at, vec![0.0, 1.0 / 3.0, 0.2]);
+    assert_eq!(scores.recall_at, vec![0.0, 0.5, 1.0]);
+    assert_close(scores.average_precision, (1.0 / 3.0 + 2.0 / 5.0) / 2.0);
+    let ideal = 1.0 + 1.0 / 3.0_f64.log2();
+    assert_close(scores.ndcg, (1.0 / 4.0_f64.log2() + 1.0 / 6.0_f64.log2()) / ideal);
+    assert_eq!(scores.lines_before_first_relevant, Some(2));
+
+    let perfect = RankingScores::of(&reference, &[20, 10, 5], &[2]);
+    assert_close(perfect.average_precision, 1.0);
+    assert_close(perfect.ndcg, 1.0);
+    assert_eq!(perfect.lines_before_first_relevant, Some(0));
+
+    let nothing = RankingScores::of(&reference, &[1, 2], &[2]);
+    assert_eq!((nothing.average_precision, nothing.ndcg), (0.0, 0.0));
+    assert_eq!(nothing.lines_before_first_relevant, None);
+}
+
+#[test]
+fn regions() {
+    let reference = HashSet::from([3, 4, 5, 6, 10, 20, 21]);
+    assert_eq!(evaluation::regions(&reference), vec![3..7, 10..11, 20..22]);
+    // the first region found in two pieces, the second one missed, half of the third one found
+    let selected = HashSet::from([3, 5, 6, 7, 21, 40]);
+    let counts = RegionCounts::of(&reference, &selected);
+    assert_eq!((counts.regions, counts.hits, counts.fragments), (3, 2, 3));
+    assert_close(counts.hit_rate(), 2.0 / 3.0);
+    assert_close(counts.coverage(), (0.75 + 0.0 + 0.5) / 3.0);
+    assert_eq!(counts.fragmentation(), Some(1.5));
+
+    let missed = RegionCounts::of(&reference, &HashSet::from([40]));
+    assert_eq!(missed.fragmentation(), None);
+    let total = [counts, missed].iter().sum::<RegionCounts>();
+    assert_eq!((total.regions, total.hits), (6, 2));
+    assert_close(total.hit_rate(), 1.0 / 3.0);
+}
+
+#[test]
+fn evaluation_of_a_dataset() {
+    let path = std::env::temp_dir().join(format!("cidiff-evaluation-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    for run in ["run1-0", "run2-0", "run3-0"] {
+        let dir = path.join("owner/repo").join(run);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("success.log"), "ok\n".repeat(10)).unwrap();
+        fs::write(dir.join("failure.log"), "ko\n".repeat(10)).unwrap();
+    }
+    let humans = path.join("humans.csv");
+    fs::write(
+        &humans,
+        "path,type,line,label\n\
+         owner/repo/run1-0,human:a,2,error\n\
+         owner/repo/run1-0,human:b,3,\n\
+         owner/repo/run2-0,human:a,1,\n",
+    )
+    .unwrap();
+    let algorithms = path.join("algorithms.csv");
+    // the third log has no relevant line, it can not be evaluated
+    fs::write(
+        &algorithms,
+        "path,type,line,score\n\
+         owner/repo/run1-0,gpt,7,0.8\n\
+         owner/repo/run1-0,gpt,3,0.9\n\
+         owner/repo/run1-0,keyword,5,\n\
+         owner/repo/run2-0,gpt,1,0.2\n\
+         owner/repo/run3-0,gpt,4,0.5\n",
+    )
+    .unwrap();
+    let dataset = Dataset::new(&path, DatasetSpec::default());
+    let parser = LogParser::new();
+    let files = [&humans, &algorithms];
+    let settings = Settings {
+        reference: "human".to_string(),
+        tolerance: 2,
+        cutoffs: vec![1],
+        by_step: true,
+    };
+
+    let selections = load_selections(&files, "human", None).unwrap();
+    assert_eq!(selections["owner/repo/run1-0"]["gpt"].ranking(), vec![3, 7]);
+    let evaluations = Evaluations::of(&selections, &settings, &dataset, &parser).unwrap();
+    assert_eq!(
+        evaluations.references,
+        vec![HashSet::from([2, 3]), HashSet::from([1])]
+    );
+    let names = evaluations
+        .evaluations
+        .iter()
+        .map(|e| e.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["gpt", "keyword", "gpt ±2", "keyword ±2"]);
+    let [gpt, keyword, _, keyword_within] = &evaluations.evaluations[..] else {
+        unreachable!()
+    };
+    assert_eq!(
+        gpt.confusions(),
+        vec![
+            Confusion {
+                true_positives: 1,
+                false_positives: 1,
+                false_negatives: 1,
+                true_negatives: 7,
+            },
+            Confusion {
+                true_positives: 1,
+                false_positives: 0,
+                false_negatives: 0,
+                true_negatives: 9,
+            },
+        ]
+    );
+    // the line 5 of the first log is 2 lines away from the relevant line 3
+    let total = keyword_within.confusions().iter().sum::<Confusion>();
+    assert_eq!(
+        (total.true_positives, total.false_positives, total.false_negatives),
+        (1, 0, 2)
+    );
+    // the logs without steps are a single step, with every line
+    assert_eq!(gpt.steps.keys().collect::<Vec<_>>(), vec![NO_STEP]);
+    assert_eq!(gpt.steps[NO_STEP], gpt.confusions().iter().sum());
+    assert!(keyword_within.steps.is_empty());
+    let means = RankingMeans::of(&gpt.rankings, 1);
+    assert_eq!((means.precision_at[0], means.found), (1.0, 2));
+    assert_eq!(RankingMeans::of(&keyword.rankings, 1).found, 0);
+
+    let comparisons = evaluations.compare(Metric::F1, 2.0, 100, 0);
+    assert_eq!(comparisons.len(), 2);
+    assert_eq!(comparisons[0].first.name(), "gpt");
+    assert_eq!(comparisons[0].second.name(), "keyword");
+    assert_eq!(comparisons[0].discordant, Some((3, 1)));
+    assert_eq!(comparisons[1].discordant, None);
+
+    // with a label, only the lines of the reference with this label are relevant
+    let selections = load_selections(&files, "human", Some("error")).unwrap();
+    let evaluations = Evaluations::of(&selections, &settings, &dataset, &parser).unwrap();
+    assert_eq!(evaluations.references, vec![HashSet::from([2])]);
+
+    fs::remove_dir_all(&path).unwrap();
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use cidiff_annotations::{ansi, steps::Folds, Command, LogFormat, LogParser};
+use regex::Regex;
+
+/// Every `<name>.log` in `tests/fixtures` is parsed and compared to the lines of `<name>.expected`.
+/// The logs are written after the output of the runners (their preambles, group markers, timestamp
+/// prefixes and escape sequences), as the dataset is not in the repository.
+#[test]
+fn fixtures_are_rendered_as_expected() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures");
+    let parser = LogParser::new();
+    let mut checked = 0;
+    for entry in fs::read_dir(&fixtures).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("log") {
+            continue;
+        }
+        let expected = fs::read_to_string(path.with_extension("expected")).unwrap();
+        let lines = parser.parse_file(&path).unwrap();
+        let contents = lines.iter().map(|l| &l.content[..]).collect::<Vec<&str>>();
+        assert_eq!(
+            contents,
+            expected.lines().collect::<Vec<&str>>(),
+            "{}",
+            path.display()
+        );
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+/// The parsing of the first annotations, to check that the legacy format keeps their line numbers.
+fn baseline_parse(file_content: &str) -> Vec<String> {
+    let timestamp_regex = Regex::new(r"(?:\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}.\d{7}Z ?)?(.*)").unwrap();
+    let ansi_color_regex = Regex::new(r"\x1b?\[(?:\d+)?(?:;\d+)*m").unwrap();
+    let mut lines = vec![];
+    for line in file_content.lines() {
+        let caps = timestamp_regex.captures(line).unwrap();
+        let cleaned = ansi_color_regex.replace_all(&caps[1], "");
+        if !cleaned.trim().is_empty() {
+            lines.push(cleaned.to_string());
+        }
+    }
+    lines
+}
+
+#[test]
+fn legacy_format_keeps_the_baseline_numbering() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures");
+    let parser = LogParser::new().format(LogFormat::Legacy);
+    for name in ["github-cargo", "github-npm", "github-pip"] {
+        let content = fs::read_to_string(fixtures.join(name).with_extension("log")).unwrap();
+        let lines = parser.parse(&content);
+        let contents = lines.iter().map(|l| l.content.clone()).collect::<Vec<_>>();
+        assert_eq!(contents, baseline_parse(&content), "{}", name);
+        assert!(lines.iter().enumerate().all(|(i, l)| l.number == i));
+    }
+    // the default parsing drops the lines that only move the cursor, which shifts the next ones
+    let content = "first\n\x1b[1G\x1b[0K\nsecond\n";
+    assert_eq!(parser.parse(content)[2].content, "second");
+    assert_eq!(LogParser::new().parse(content)[1].content, "second");
+}
+
+#[test]
+fn line_numbers_skip_the_empty_lines() {
+    let lines = LogParser::new().parse("first\n\n\x1b[1G\x1b[0K\nsecond\n");
+    let numbers = lines.iter().map(|l| (l.raw_number, l.number)).collect::<Vec<_>>();
+    assert_eq!(numbers, vec![(0, 0), (3, 1)]);
+}
+
+#[test]
+fn text_looking_like_a_color_code_is_kept() {
+    assert_eq!(ansi::render("index [0m and [1;31m"), "index [0m and [1;31m");
+}
+
+#[test]
+fn oversized_parameters_saturate() {
+    assert_eq!(ansi::render("a\x1b[99999999999999999999Cb"), "a");
+    assert_eq!(ansi::render("abc\x1b[2D\x1b[99999999999999999999X"), "a");
+    assert_eq!(ansi::render("abc\x1b[2D\x1b[99999999999999999999P"), "a");
+    assert_eq!(ansi::render("abc\x1b[99999999999999999999Dz"), "zbc");
+}
+
+#[test]
+fn carriage_return_overwrites_the_line() {
+    assert_eq!(ansi::render("progress 10%\rprogress 100%"), "progress 100%");
+    assert_eq!(ansi::render("abcdef\rxy"), "xycdef");
+    assert_eq!(ansi::render("abcdef\rxy\x1b[K"), "xy");
+}
+
+#[test]
+fn cursor_movements_and_erasures() {
+    assert_eq!(ansi::render("abc\x1b[2Dz"), "azc");
+    assert_eq!(ansi::render("abc\x1b[5Gz"), "abc z");
+    assert_eq!(ansi::render("abcdef\x1b[3G\x1b[1K"), "   def");
+    assert_eq!(ansi::render("abcdef\x1b[2K"), "");
+    assert_eq!(ansi::render("abcdef\x1b[2G\x1b[2P"), "adef");
+}
+
+#[test]
+fn osc_strings_are_removed() {
+    assert_eq!(
+        ansi::render("see \x1b]8;;https://example.com\x1b\\the docs\x1b]8;;\x07 here"),
+        "see the docs here"
+    );
+}
+
+#[test]
+fn github_logs_running_jenkins_commands_are_detected() {
+    let log = "2024-03-12T14:05:00.1234567Z ##[group]Run ./ci.sh\n\
+               2024-03-12T14:05:01.1234567Z Started by upstream project\n\
+               2024-03-12T14:05:02.1234567Z [Pipeline] stage\n";
+    assert_eq!(LogFormat::detect(log).name(), "github");
+    assert_eq!(
+        LogFormat::detect("Started by user admin\n[Pipeline] stage\n").name(),
+        "jenkins"
+    );
+}
+
+#[test]
+fn lines_are_tagged_with_their_step() {
+    let log = LogParser::new().parse_log(
+        "##[group]Run cargo test\n\
+         cargo test\n\
+         ##[endgroup]\n\
+         ##[group]Tests\n\
+         test a ... ok\n\
+         ##[endgroup]\n\
+         ##[error]Process completed with exit code 101.\n\
+         ##[group]Run actions/cache\n\
+         ##[endgroup]\n",
+    );
+    let names = log.sections.iter().map(|s| &s.name[..]).collect::<Vec<&str>>();
+    assert_eq!(names, vec!["Run cargo test", "Tests", "Run actions/cache"]);
+    assert_eq!((log.sections[0].start, log.sections[0].end), (0, 6));
+    assert_eq!(log.sections[1].parent, Some(0));
+    assert_eq!(log.lines[4].section, Some(1));
+    assert_eq!(log.lines[6].command, Some(Command::Error));
+    assert_eq!(log.step_of(4), Some(0));
+    assert_eq!(log.step_of(7), Some(2));
+    assert_eq!(log.visible_lines(&HashSet::from([0])), vec![0, 7, 8]);
+}
+
+#[test]
+fn folds_keep_the_highlighted_line() {
+    let log = LogParser::new().parse_log(
+        "##[group]Run cargo test\n\
+         cargo test\n\
+         ##[endgroup]\n\
+         ##[group]Tests\n\
+         test a ... ok\n\
+         ##[endgroup]\n\
+         ##[error]Process completed with exit code 101.\n\
+         ##[group]Run actions/cache\n\
+         ##[endgroup]\n",
+    );
+    let mut folds = Folds::new(&log);
+    assert_eq!(folds.rows(), (0..9).collect::<Vec<_>>());
+    // the row of the line opening the folded section is highlighted
+    assert_eq!(folds.toggle(&log, 4), 3);
+    assert_eq!(folds.rows(), vec![0, 1, 2, 3, 6, 7, 8]);
+    // something is folded: everything is unfolded
+    assert_eq!(folds.toggle_all(&log, 5), 7);
+    assert_eq!(folds.rows(), (0..9).collect::<Vec<_>>());
+    assert_eq!(folds.toggle_all(&log, 4), 0);
+    assert_eq!(folds.rows(), vec![0, 7]);
+    assert_eq!(folds.toggle(&log, 1), 1);
+    assert_eq!(folds.rows(), vec![0, 7, 8]);
+    // out of the rows, nothing changes
+    assert_eq!(folds.toggle(&log, 10), 10);
+    assert_eq!(folds.rows(), vec![0, 7, 8]);
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use cidiff_annotations::{
+    index::{IndexEntry, LogStats},
+    sampling::Strategy,
+    Sampling,
+};
+
+fn entry(path: &str, language: &str, failure_lines: usize) -> IndexEntry {
+    let stats = |lines| LogStats {
+        lines,
+        bytes: 0,
+        mtime: 0,
+    };
+    IndexEntry {
+        path: PathBuf::from(path),
+        language: Some(language.to_string()),
+        success: stats(10),
+        failure: stats(failure_lines),
+    }
+}
+
+/// 30 python pairs in 3 repositories, 3 rust pairs in 1 repository.
+fn population() -> Vec<IndexEntry> {
+    let mut entries = vec![];
+    for i in 0..30 {
+        entries.push(entry(&format!("py/repo{}/run{}-0", i % 3, i), "Python", i * 10));
+    }
+    for i in 0..3 {
+        entries.push(entry(&format!("rs/repo/run{}-0", i), "Rust", i));
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+#[test]
+fn the_same_seed_gives_the_same_sample() {
+    let entries = population();
+    let population = entries.iter().collect::<Vec<_>>();
+    for strategy in [
+        Strategy::Uniform,
+        Strategy::Language,
+        Strategy::Repository,
+        Strategy::Length,
+    ] {
+        let sampling = Sampling {
+            size: 10,
+            strategy,
+            ..Sampling::default()
+        };
+        let sample = sampling.sample(&population).unwrap();
+        assert_eq!(sample.paths.len(), 10);
+        assert_eq!(sample.paths, sampling.sample(&population).unwrap().paths);
+        let other_seed = Sampling { seed: 1, ..sampling };
+        assert_ne!(sample.paths, other_seed.sample(&population).unwrap().paths);
+    }
+}
+
+#[test]
+fn the_strata_are_drawn_evenly() {
+    let entries = population();
+    let population = entries.iter().collect::<Vec<_>>();
+    let sampling = Sampling {
+        size: 10,
+        strategy: Strategy::Language,
+        ..Sampling::default()
+    };
+    let sample = sampling.sample(&population).unwrap();
+    // the rust stratum is smaller than its share, the rest goes to python
+    assert_eq!(
+        sample.strata,
+        [("Python".to_string(), 7), ("Rust".to_string(), 3)].into()
+    );
+
+    let sampling = Sampling {
+        size: 8,
+        strategy: Strategy::Repository,
+        ..Sampling::default()
+    };
+    let sample = sampling.sample(&population).unwrap();
+    assert_eq!(
+        sample.strata.values().copied().collect::<Vec<_>>(),
+        vec![2, 2, 2, 2]
+    );
+}
+
+#[test]
+fn the_sample_is_never_larger_than_the_population() {
+    let entries = population();
+    let population = entries.iter().collect::<Vec<_>>();
+    for strategy in [Strategy::Uniform, Strategy::Repository] {
+        let sampling = Sampling {
+            size: 1000,
+            strategy,
+            ..Sampling::default()
+        };
+        let sample = sampling.sample(&population).unwrap();
+        let counts = sample.paths.iter().fold(HashMap::new(), |mut counts, p| {
+            *counts.entry(p).or_insert(0) += 1;
+            counts
+        });
+        assert_eq!(counts.len(), entries.len());
+        assert!(counts.values().all(|&n| n == 1));
+    }
+}
@@ -0,0 +1,78 @@
+use cidiff_annotations::statistics;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-4,
+        "{} instead of {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn bootstrap_is_reproducible() {
+    let items = (0..100).map(|i| (i % 10) as f64).collect::<Vec<_>>();
+    let mean = |items: &[f64]| [items.iter().sum::<f64>() / items.len() as f64];
+    let interval = |seed| {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        statistics::bootstrap(&items, 1000, 0.95, &mut rng, mean).unwrap()[0]
+    };
+    let [first, second] = [interval(0), interval(0)];
+    assert_eq!(first, second);
+    assert!(first.low < 4.5 && 4.5 < first.high);
+    assert!(first.high - first.low < 2.0);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    assert_eq!(statistics::bootstrap(&[], 1000, 0.95, &mut rng, mean), None);
+    let constant = statistics::bootstrap(&[1.0; 10], 100, 0.95, &mut rng, mean).unwrap()[0];
+    assert_eq!((constant.low, constant.high), (1.0, 1.0));
+}
+
+#[test]
+fn wilcoxon_signed_rank() {
+    // Darwin's data on the heights of crossed and self-fertilized plants
+    let differences = [
+        6.0, 8.0, 14.0, 16.0, 23.0, 24.0, 28.0, 29.0, 41.0, -48.0, 49.0, 56.0, 60.0, -67.0, 75.0,
+    ];
+    let test = statistics::wilcoxon_signed_rank(&differences).unwrap();
+    assert_eq!(test.statistic, 24.0);
+    assert_close(test.p_value, 0.04377);
+    // the null differences are dropped
+    let mut with_zeros = differences.to_vec();
+    with_zeros.extend([0.0, 0.0]);
+    assert_eq!(statistics::wilcoxon_signed_rank(&with_zeros), Some(test));
+    assert_eq!(statistics::wilcoxon_signed_rank(&[0.0, 0.0]), None);
+}
+
+#[test]
+fn mcnemar() {
+    // few discordant lines: the exact binomial test
+    let test = statistics::mcnemar(10, 2).unwrap();
+    assert_close(test.p_value, 0.038574);
+    let test = statistics::mcnemar(40, 20).unwrap();
+    assert_close(test.statistic, 361.0 / 60.0);
+    assert_close(test.p_value, 0.014171);
+    assert_eq!(statistics::mcnemar(3, 3).unwrap().p_value, 1.0);
+    assert_eq!(statistics::mcnemar(0, 0), None);
+}
+
+#[test]
+fn paired_permutation() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    // every sign is tried: only all positive and all negative are as far from 0
+    let test = statistics::paired_permutation(&[1.0, 2.0, 3.0], 1000, &mut rng).unwrap();
+    assert_eq!(test.statistic, 2.0);
+    assert_eq!(test.p_value, 0.25);
+
+    let differences = (0..50).map(|i| (i % 7) as f64 / 10.0 - 0.2).collect::<Vec<_>>();
+    let p_value = |seed| {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        statistics::paired_permutation(&differences, 2000, &mut rng)
+            .unwrap()
+            .p_value
+    };
+    assert_eq!(p_value(1), p_value(1));
+    assert!(p_value(1) < 0.01);
+}
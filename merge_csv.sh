@@ -8,10 +8,42 @@ fi
 output_file="$1"
 shift
 
-head -n 1 "$1" > "$output_file"
-
-for file in "$@"; do
-  tail -n +2 "$file" >> "$output_file"
-done
+# The files do not have the same columns (e.g. the rank of gpt, the label of the humans): the
+# header is the union of their headers, and each record is moved under it, empty where its file
+# has no such column.
+awk '
+# split a csv record, keeping the quoted fields whole
+function split_csv(record, fields,    n, field, quoted, i, c) {
+  n = 0; field = ""; quoted = 0
+  for (i = 1; i <= length(record); i++) {
+    c = substr(record, i, 1)
+    if (c == "\"") quoted = !quoted
+    if (c == "," && !quoted) { fields[++n] = field; field = "" } else field = field c
+  }
+  fields[++n] = field
+  return n
+}
+{ sub(/\r$/, "") }
+NR == FNR {
+  # the headers of every file first
+  n = split_csv($0, names)
+  for (i = 1; i <= n; i++) if (!(names[i] in index_of)) { index_of[names[i]] = ++columns; header[columns] = names[i] }
+  next
+}
+FNR == 1 {
+  if (!printed) {
+    for (i = 1; i <= columns; i++) printf "%s%s", header[i], (i < columns ? "," : "\n")
+    printed = 1
+  }
+  split("", position)
+  n = split_csv($0, names)
+  for (i = 1; i <= n; i++) position[index_of[names[i]]] = i
+  next
+}
+{
+  n = split_csv($0, fields)
+  for (i = 1; i <= columns; i++) printf "%s%s", (i in position ? fields[position[i]] : ""), (i < columns ? "," : "\n")
+}
+' <(for file in "$@"; do head -n 1 "$file"; done) "$@" > "$output_file"
 
 echo "Merged CSV files into $output_file successfully."